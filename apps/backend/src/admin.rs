@@ -0,0 +1,78 @@
+//! Account moderation endpoints gated on the access token's `role` claim —
+//! cheaper than a DB lookup per admin request, at the cost of a stale role
+//! surviving until the token expires if someone's admin rights are revoked.
+
+use crate::auth;
+use crate::db;
+use crate::errors::AppError;
+use crate::models::Claims;
+use actix_web::{web, HttpResponse};
+use sqlx::{Pool, Postgres};
+use uuid::Uuid;
+
+fn require_admin(claims: &Claims) -> Result<(), AppError> {
+    if claims.role.as_deref() == Some("admin") {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("Admin role required".to_string()))
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/block",
+    params(
+        ("id" = Uuid, Path, description = "User to block")
+    ),
+    responses(
+        (status = 200, description = "User blocked"),
+        (status = 403, description = "Admin role required")
+    ),
+    security(("bearer" = [])),
+    tag = "admin"
+)]
+pub async fn block_user(
+    pool: web::Data<Pool<Postgres>>,
+    redis_client: web::Data<redis::Client>,
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&claims)?;
+    let user_id = user_id.into_inner();
+
+    db::set_user_blocked(&pool, &user_id, true).await?;
+    auth::mark_user_blocked(&redis_client, &user_id).await?;
+    // Blocking invalidates every existing session, not just future requests,
+    // so a refresh already in flight can't hand the account a new access token.
+    auth::delete_all_refresh_families(&redis_client, &user_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "User blocked" })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/unblock",
+    params(
+        ("id" = Uuid, Path, description = "User to unblock")
+    ),
+    responses(
+        (status = 200, description = "User unblocked"),
+        (status = 403, description = "Admin role required")
+    ),
+    security(("bearer" = [])),
+    tag = "admin"
+)]
+pub async fn unblock_user(
+    pool: web::Data<Pool<Postgres>>,
+    redis_client: web::Data<redis::Client>,
+    claims: web::ReqData<Claims>,
+    user_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    require_admin(&claims)?;
+    let user_id = user_id.into_inner();
+
+    db::set_user_blocked(&pool, &user_id, false).await?;
+    auth::unmark_user_blocked(&redis_client, &user_id).await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({ "message": "User unblocked" })))
+}
@@ -1,19 +1,27 @@
 use crate::db;
+use crate::errors::AppError;
+use crate::mailer;
 use crate::models::{
-    AuthResponse, Claims, LoginRequest, RefreshRequest, RegisterRequest, UserResponse,
+    AuthResponse, Claims, ListSessionsResponse, LoginRequest, MagicLinkRequest,
+    MagicLinkVerifyQuery, RefreshRequest, RegisterRequest, SessionResponse, UserResponse,
 };
 use actix_web::{dev::ServiceRequest, web, Error, HttpMessage, HttpResponse};
 use actix_web_httpauth::extractors::bearer::BearerAuth;
+use base64::Engine;
 use bcrypt::{hash, verify, DEFAULT_COST};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use rand::rngs::OsRng;
+use rand::RngCore;
 use redis::AsyncCommands;
 use sqlx::{Pool, Postgres};
 use std::env;
 use uuid::Uuid;
 
 const JWT_SECRET: &str = "your-secret-key-change-in-production";
-const ACCESS_TOKEN_EXPIRY_HOURS: i64 = 1; // 1 hour for access token
+pub(crate) const ACCESS_TOKEN_EXPIRY_HOURS: i64 = 1; // 1 hour for access token
 const REFRESH_TOKEN_EXPIRY_DAYS: i64 = 30; // 30 days for refresh token
+/// How long a magic-link token survives in Redis before it must be used.
+const MAGIC_LINK_TTL_SECONDS: u64 = 600;
 
 pub fn get_jwt_secret() -> String {
     env::var("JWT_SECRET").unwrap_or_else(|_| JWT_SECRET.to_string())
@@ -22,6 +30,7 @@ pub fn get_jwt_secret() -> String {
 pub fn create_access_token(
     user_id: &Uuid,
     email: &str,
+    role: &str,
 ) -> Result<String, jsonwebtoken::errors::Error> {
     let expiration = chrono::Utc::now()
         .checked_add_signed(chrono::Duration::hours(ACCESS_TOKEN_EXPIRY_HOURS))
@@ -32,6 +41,9 @@ pub fn create_access_token(
         sub: user_id.to_string(),
         email: email.to_string(),
         exp: expiration,
+        jti: Uuid::new_v4().to_string(),
+        family: None,
+        role: Some(role.to_string()),
     };
 
     encode(
@@ -41,26 +53,38 @@ pub fn create_access_token(
     )
 }
 
+/// Returns the encoded refresh token along with its `jti`, which the caller
+/// persists in Redis so rotation/reuse-detection has something to compare
+/// against. `family` identifies the session (one per device/browser) this
+/// token belongs to; pass a fresh `Uuid::new_v4()` at login/registration and
+/// the same family again on every subsequent rotation.
 pub fn create_refresh_token(
     user_id: &Uuid,
     email: &str,
-) -> Result<String, jsonwebtoken::errors::Error> {
+    family: &str,
+) -> Result<(String, String), jsonwebtoken::errors::Error> {
     let expiration = chrono::Utc::now()
         .checked_add_signed(chrono::Duration::days(REFRESH_TOKEN_EXPIRY_DAYS))
         .expect("valid timestamp")
         .timestamp() as usize;
 
+    let jti = Uuid::new_v4().to_string();
     let claims = Claims {
         sub: user_id.to_string(),
         email: email.to_string(),
         exp: expiration,
+        jti: jti.clone(),
+        family: Some(family.to_string()),
+        role: None,
     };
 
-    encode(
+    let token = encode(
         &Header::default(),
         &claims,
         &EncodingKey::from_secret(get_jwt_secret().as_bytes()),
-    )
+    )?;
+
+    Ok((token, jti))
 }
 
 pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
@@ -72,43 +96,266 @@ pub fn verify_jwt(token: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
     .map(|data| data.claims)
 }
 
-async fn store_refresh_token(
+fn refresh_token_key(user_id: &Uuid, family: &str) -> String {
+    format!("refresh:{}:{}", user_id, family)
+}
+
+/// Tracks which families currently exist for a user, so they can be listed
+/// or torn down all at once without a Redis `KEYS` scan.
+fn refresh_families_key(user_id: &Uuid) -> String {
+    format!("refresh_families:{}", user_id)
+}
+
+/// Stores only the current refresh token's `jti`, not the token itself, so a
+/// leaked Redis dump can't be replayed as a bearer credential. One user can
+/// have many families (devices) live at once, each tracked under its own key.
+pub(crate) async fn store_refresh_token(
     redis_client: &redis::Client,
     user_id: &Uuid,
-    refresh_token: &str,
+    family: &str,
+    jti: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut con = redis_client.get_async_connection().await?;
-    let key = format!("refresh_token:{}", user_id);
-    let ttl = REFRESH_TOKEN_EXPIRY_DAYS * 24 * 3600; // Convert to seconds
+    let ttl = (REFRESH_TOKEN_EXPIRY_DAYS * 24 * 3600) as u64;
 
-    con.set_ex::<_, _, ()>(&key, refresh_token, ttl as u64)
+    con.set_ex::<_, _, ()>(refresh_token_key(user_id, family), jti, ttl)
         .await?;
+    let families_key = refresh_families_key(user_id);
+    con.sadd::<_, _, ()>(&families_key, family).await?;
+    con.expire::<_, ()>(&families_key, ttl as i64).await?;
     Ok(())
 }
 
-async fn verify_refresh_token(
+enum RefreshTokenStatus {
+    Valid,
+    /// The JWT is well-formed but its `jti` doesn't match the one on file
+    /// for this family, meaning an already-rotated token was replayed.
+    Reused,
+    /// Nothing on file for this family (expired, revoked, or never issued).
+    Unknown,
+}
+
+async fn check_refresh_token(
     redis_client: &redis::Client,
     user_id: &Uuid,
-    refresh_token: &str,
-) -> Result<bool, Box<dyn std::error::Error>> {
+    family: &str,
+    jti: &str,
+) -> Result<RefreshTokenStatus, Box<dyn std::error::Error>> {
     let mut con = redis_client.get_async_connection().await?;
-    let key = format!("refresh_token:{}", user_id);
+    let stored_jti: Option<String> = con.get(refresh_token_key(user_id, family)).await?;
+    Ok(match stored_jti {
+        Some(stored) if stored == jti => RefreshTokenStatus::Valid,
+        Some(_) => RefreshTokenStatus::Reused,
+        None => RefreshTokenStatus::Unknown,
+    })
+}
 
-    let stored_token: Option<String> = con.get(&key).await?;
-    Ok(stored_token.as_deref() == Some(refresh_token))
+/// Lists the user's live session families, e.g. for `GET /api/auth/sessions`.
+async fn list_refresh_families(
+    redis_client: &redis::Client,
+    user_id: &Uuid,
+) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let mut con = redis_client.get_async_connection().await?;
+    Ok(con.smembers(refresh_families_key(user_id)).await?)
 }
 
-async fn delete_refresh_token(
+/// Revokes a single device/session, leaving the user's other families intact.
+async fn delete_refresh_family(
     redis_client: &redis::Client,
     user_id: &Uuid,
+    family: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut con = redis_client.get_async_connection().await?;
-    let key = format!("refresh_token:{}", user_id);
+    con.del::<_, ()>(refresh_token_key(user_id, family)).await?;
+    con.srem::<_, _, ()>(refresh_families_key(user_id), family)
+        .await?;
+    Ok(())
+}
 
-    con.del::<_, ()>(&key).await?;
+/// Revokes every session for a user at once — used on logout, when
+/// refresh-token reuse indicates a stolen token, and when an admin blocks an
+/// account, so one compromised or disabled account can't be worked around
+/// by rotating just one family.
+pub(crate) async fn delete_all_refresh_families(
+    redis_client: &redis::Client,
+    user_id: &Uuid,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut con = redis_client.get_async_connection().await?;
+    let families_key = refresh_families_key(user_id);
+    let families: Vec<String> = con.smembers(&families_key).await?;
+    for family in families {
+        con.del::<_, ()>(refresh_token_key(user_id, &family)).await?;
+    }
+    con.del::<_, ()>(&families_key).await?;
+    Ok(())
+}
+
+fn blocked_key(user_id: &Uuid) -> String {
+    format!("blocked:{}", user_id)
+}
+
+/// Lets `validator` reject a blocked account's still-unexpired access token
+/// without a Postgres round trip on every authenticated request; `is_blocked`
+/// on the `users` row remains the durable source of truth.
+pub(crate) async fn mark_user_blocked(
+    redis_client: &redis::Client,
+    user_id: &Uuid,
+) -> Result<(), redis::RedisError> {
+    let mut con = redis_client.get_async_connection().await?;
+    con.set(blocked_key(user_id), "1").await
+}
+
+pub(crate) async fn unmark_user_blocked(
+    redis_client: &redis::Client,
+    user_id: &Uuid,
+) -> Result<(), redis::RedisError> {
+    let mut con = redis_client.get_async_connection().await?;
+    con.del(blocked_key(user_id)).await
+}
+
+async fn is_user_blocked(
+    redis_client: &redis::Client,
+    user_id: &Uuid,
+) -> Result<bool, redis::RedisError> {
+    let mut con = redis_client.get_async_connection().await?;
+    con.exists(blocked_key(user_id)).await
+}
+
+fn magic_link_key(token: &str) -> String {
+    format!("magic:{}", token)
+}
+
+fn generate_magic_link_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+async fn store_magic_link_token(
+    redis_client: &redis::Client,
+    token: &str,
+    user_id: &Uuid,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut con = redis_client.get_async_connection().await?;
+    con.set_ex::<_, _, ()>(magic_link_key(token), user_id.to_string(), MAGIC_LINK_TTL_SECONDS)
+        .await?;
     Ok(())
 }
 
+/// Looks up and immediately deletes the token so it can only be redeemed once.
+async fn take_magic_link_token(
+    redis_client: &redis::Client,
+    token: &str,
+) -> Result<Option<Uuid>, Box<dyn std::error::Error>> {
+    let mut con = redis_client.get_async_connection().await?;
+    let key = magic_link_key(token);
+
+    let user_id: Option<String> = con.get(&key).await?;
+    con.del::<_, ()>(&key).await?;
+
+    Ok(match user_id {
+        Some(user_id) => Some(Uuid::parse_str(&user_id)?),
+        None => None,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/magic-link",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 200, description = "Magic link sent"),
+    ),
+    tag = "auth"
+)]
+pub async fn request_magic_link(
+    pool: web::Data<Pool<Postgres>>,
+    redis_client: web::Data<redis::Client>,
+    req: web::Json<MagicLinkRequest>,
+) -> Result<HttpResponse, AppError> {
+    // No password is ever checked for this account, so any random hash
+    // works; only OAuth/magic-link flows will ever authenticate it.
+    let unusable_password_hash = hash(Uuid::new_v4().to_string(), DEFAULT_COST)
+        .map_err(|e| AppError::Internal(format!("hash error: {}", e)))?;
+    let user =
+        db::get_or_create_user_by_email(&pool, &req.email, &unusable_password_hash).await?;
+
+    let token = generate_magic_link_token();
+    store_magic_link_token(&redis_client, &token, &user.id)
+        .await?;
+
+    let frontend_url =
+        env::var("FRONTEND_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+    let link = format!(
+        "{}/auth/magic-link/verify?token={}",
+        frontend_url.trim_end_matches('/'),
+        token
+    );
+
+    if let Err(e) = mailer::send_email(
+        &user.email,
+        "Your sign-in link",
+        &format!(
+            "Click the link below to sign in. It expires in 10 minutes.\n\n{}",
+            link
+        ),
+    ) {
+        log::error!("failed to send magic link email: {}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Magic link sent"
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/magic-link/verify",
+    params(MagicLinkVerifyQuery),
+    responses(
+        (status = 200, description = "Signed in successfully", body = AuthResponse),
+        (status = 401, description = "Invalid or expired token"),
+        (status = 403, description = "Account is blocked")
+    ),
+    tag = "auth"
+)]
+pub async fn verify_magic_link(
+    pool: web::Data<Pool<Postgres>>,
+    redis_client: web::Data<redis::Client>,
+    query: web::Query<MagicLinkVerifyQuery>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = take_magic_link_token(&redis_client, &query.token)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired token".to_string()))?;
+
+    let user = db::get_user_by_id(&pool, &user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+    if user.is_blocked {
+        return Err(AppError::Forbidden("Account is blocked".to_string()));
+    }
+
+    let access_token = create_access_token(&user.id, &user.email, &user.role)?;
+
+    let family = Uuid::new_v4().to_string();
+    let (refresh_token, refresh_jti) = create_refresh_token(&user.id, &user.email, &family)?;
+
+    store_refresh_token(&redis_client, &user.id, &family, &refresh_jti)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_EXPIRY_HOURS * 3600,
+        user: UserResponse {
+            id: user.id,
+            email: user.email,
+        },
+    }))
+}
+
 #[utoipa::path(
     post,
     path = "/api/auth/register",
@@ -124,48 +371,21 @@ pub async fn register(
     pool: web::Data<Pool<Postgres>>,
     redis_client: web::Data<redis::Client>,
     req: web::Json<RegisterRequest>,
-) -> Result<HttpResponse, Error> {
-    let existing_user = db::get_user_by_email(&pool, &req.email)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    if existing_user.is_some() {
-        return Ok(HttpResponse::Conflict().json(serde_json::json!({
-            "error": "User already exists"
-        })));
-    }
+) -> Result<HttpResponse, AppError> {
+    let password_hash = hash(&req.password, DEFAULT_COST)
+        .map_err(|e| AppError::Internal(format!("hash error: {}", e)))?;
 
-    let password_hash = hash(&req.password, DEFAULT_COST).map_err(|e| {
-        log::error!("Hash error: {}", e);
-        actix_web::error::ErrorInternalServerError("Hash error")
-    })?;
-
-    let user = db::create_user(&pool, &req.email, &password_hash)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let access_token = create_access_token(&user.id, &user.email).map_err(|e| {
-        log::error!("JWT error: {}", e);
-        actix_web::error::ErrorInternalServerError("JWT error")
-    })?;
-
-    let refresh_token = create_refresh_token(&user.id, &user.email).map_err(|e| {
-        log::error!("JWT error: {}", e);
-        actix_web::error::ErrorInternalServerError("JWT error")
-    })?;
-
-    store_refresh_token(&redis_client, &user.id, &refresh_token)
-        .await
-        .map_err(|e| {
-            log::error!("Redis error: {}", e);
-            actix_web::error::ErrorInternalServerError("Redis error")
-        })?;
+    // No pre-check SELECT: `create_user`'s unique_violation on a duplicate
+    // email turns into AppError::Conflict via the sqlx::Error conversion.
+    let user = db::create_user(&pool, &req.email, &password_hash).await?;
+
+    let access_token = create_access_token(&user.id, &user.email, &user.role)?;
+
+    let family = Uuid::new_v4().to_string();
+    let (refresh_token, refresh_jti) = create_refresh_token(&user.id, &user.email, &family)?;
+
+    store_refresh_token(&redis_client, &user.id, &family, &refresh_jti)
+        .await?;
 
     Ok(HttpResponse::Ok().json(AuthResponse {
         access_token,
@@ -185,7 +405,8 @@ pub async fn register(
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = AuthResponse),
-        (status = 401, description = "Invalid credentials")
+        (status = 401, description = "Invalid credentials"),
+        (status = 403, description = "Account is blocked")
     ),
     tag = "auth"
 )]
@@ -193,13 +414,8 @@ pub async fn login(
     pool: web::Data<Pool<Postgres>>,
     redis_client: web::Data<redis::Client>,
     req: web::Json<LoginRequest>,
-) -> Result<HttpResponse, Error> {
-    let user = db::get_user_by_email(&pool, &req.email)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+) -> Result<HttpResponse, AppError> {
+    let user = db::get_user_by_email(&pool, &req.email).await?;
 
     let user = match user {
         Some(u) => u,
@@ -210,10 +426,8 @@ pub async fn login(
         }
     };
 
-    let valid = verify(&req.password, &user.password_hash).map_err(|e| {
-        log::error!("Verify error: {}", e);
-        actix_web::error::ErrorInternalServerError("Verify error")
-    })?;
+    let valid = verify(&req.password, &user.password_hash)
+        .map_err(|e| AppError::Internal(format!("verify error: {}", e)))?;
 
     if !valid {
         return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
@@ -221,22 +435,17 @@ pub async fn login(
         })));
     }
 
-    let access_token = create_access_token(&user.id, &user.email).map_err(|e| {
-        log::error!("JWT error: {}", e);
-        actix_web::error::ErrorInternalServerError("JWT error")
-    })?;
+    if user.is_blocked {
+        return Err(AppError::Forbidden("Account is blocked".to_string()));
+    }
 
-    let refresh_token = create_refresh_token(&user.id, &user.email).map_err(|e| {
-        log::error!("JWT error: {}", e);
-        actix_web::error::ErrorInternalServerError("JWT error")
-    })?;
+    let access_token = create_access_token(&user.id, &user.email, &user.role)?;
 
-    store_refresh_token(&redis_client, &user.id, &refresh_token)
-        .await
-        .map_err(|e| {
-            log::error!("Redis error: {}", e);
-            actix_web::error::ErrorInternalServerError("Redis error")
-        })?;
+    let family = Uuid::new_v4().to_string();
+    let (refresh_token, refresh_jti) = create_refresh_token(&user.id, &user.email, &family)?;
+
+    store_refresh_token(&redis_client, &user.id, &family, &refresh_jti)
+        .await?;
 
     Ok(HttpResponse::Ok().json(AuthResponse {
         access_token,
@@ -264,57 +473,48 @@ pub async fn refresh(
     pool: web::Data<Pool<Postgres>>,
     redis_client: web::Data<redis::Client>,
     req: web::Json<RefreshRequest>,
-) -> Result<HttpResponse, Error> {
-    let claims = verify_jwt(&req.refresh_token).map_err(|e| {
-        log::error!("JWT validation error: {}", e);
-        actix_web::error::ErrorUnauthorized("Invalid refresh token")
-    })?;
-
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    // Verify the refresh token is stored in Redis
-    let is_valid = verify_refresh_token(&redis_client, &user_id, &req.refresh_token)
-        .await
-        .map_err(|e| {
-            log::error!("Redis error: {}", e);
-            actix_web::error::ErrorInternalServerError("Redis error")
-        })?;
-
-    if !is_valid {
-        return Err(actix_web::error::ErrorUnauthorized("Invalid refresh token"));
-    }
-
-    let user = db::get_user_by_id(&pool, &user_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let claims = verify_jwt(&req.refresh_token)
+        .map_err(|_| AppError::Unauthorized("Invalid refresh token".to_string()))?;
+
+    let user_id = Uuid::parse_str(&claims.sub)?;
+    let family = claims
+        .family
+        .clone()
+        .ok_or_else(|| AppError::Unauthorized("Invalid refresh token".to_string()))?;
+
+    // Check the token's jti against the one on file for this family.
+    let status = check_refresh_token(&redis_client, &user_id, &family, &claims.jti)
+        .await?;
 
-    let user = match user {
-        Some(u) => u,
-        None => {
-            return Err(actix_web::error::ErrorUnauthorized("User not found"));
+    match status {
+        RefreshTokenStatus::Valid => {}
+        RefreshTokenStatus::Reused => {
+            // The jti on file doesn't match: this token was already rotated
+            // away, so someone is replaying a stolen refresh token. Kill
+            // every session for this user, not just this family, since we
+            // can't tell which other sessions the thief has also touched.
+            log::warn!("Refresh token reuse detected for user {}", user_id);
+            delete_all_refresh_families(&redis_client, &user_id).await.ok();
+            return Err(AppError::Unauthorized(
+                "Refresh token reuse detected; all sessions revoked".to_string(),
+            ));
         }
-    };
+        RefreshTokenStatus::Unknown => {
+            return Err(AppError::Unauthorized("Invalid refresh token".to_string()));
+        }
+    }
 
-    let access_token = create_access_token(&user.id, &user.email).map_err(|e| {
-        log::error!("JWT error: {}", e);
-        actix_web::error::ErrorInternalServerError("JWT error")
-    })?;
+    let user = db::get_user_by_id(&pool, &user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
 
-    let new_refresh_token = create_refresh_token(&user.id, &user.email).map_err(|e| {
-        log::error!("JWT error: {}", e);
-        actix_web::error::ErrorInternalServerError("JWT error")
-    })?;
+    let access_token = create_access_token(&user.id, &user.email, &user.role)?;
 
-    store_refresh_token(&redis_client, &user.id, &new_refresh_token)
-        .await
-        .map_err(|e| {
-            log::error!("Redis error: {}", e);
-            actix_web::error::ErrorInternalServerError("Redis error")
-        })?;
+    let (new_refresh_token, new_jti) = create_refresh_token(&user.id, &user.email, &family)?;
+
+    store_refresh_token(&redis_client, &user.id, &family, &new_jti)
+        .await?;
 
     Ok(HttpResponse::Ok().json(AuthResponse {
         access_token,
@@ -343,24 +543,78 @@ pub async fn refresh(
 pub async fn logout(
     redis_client: web::Data<redis::Client>,
     claims: web::ReqData<Claims>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    delete_refresh_token(&redis_client, &user_id)
-        .await
-        .map_err(|e| {
-            log::error!("Redis error: {}", e);
-            actix_web::error::ErrorInternalServerError("Redis error")
-        })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    // Logout signs the caller out everywhere; use DELETE /auth/sessions/{family}
+    // to revoke a single device instead.
+    delete_all_refresh_families(&redis_client, &user_id)
+        .await?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "message": "Logged out successfully"
     })))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/auth/sessions",
+    responses(
+        (status = 200, description = "Active sessions (one per device) for the caller", body = ListSessionsResponse),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn list_sessions(
+    redis_client: web::Data<redis::Client>,
+    claims: web::ReqData<Claims>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let families = list_refresh_families(&redis_client, &user_id)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(ListSessionsResponse {
+        sessions: families
+            .into_iter()
+            .map(|family| SessionResponse { family })
+            .collect(),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/auth/sessions/{family}",
+    params(
+        ("family" = String, Path, description = "Session family id from GET /api/auth/sessions")
+    ),
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    tag = "auth"
+)]
+pub async fn revoke_session(
+    redis_client: web::Data<redis::Client>,
+    claims: web::ReqData<Claims>,
+    family: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    delete_refresh_family(&redis_client, &user_id, &family.into_inner())
+        .await?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Session revoked"
+    })))
+}
+
 #[utoipa::path(
     get,
     path = "/api/auth/me",
@@ -376,40 +630,49 @@ pub async fn logout(
 pub async fn me(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    let user = db::get_user_by_id(&pool, &user_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
-
-    match user {
-        Some(u) => Ok(HttpResponse::Ok().json(UserResponse {
-            id: u.id,
-            email: u.email,
-        })),
-        None => Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "error": "User not found"
-        }))),
-    }
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let user = db::get_user_by_id(&pool, &user_id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("User not found".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(UserResponse {
+        id: user.id,
+        email: user.email,
+    }))
 }
 
 pub async fn validator(
     req: ServiceRequest,
     credentials: BearerAuth,
 ) -> Result<ServiceRequest, (Error, ServiceRequest)> {
-    match verify_jwt(credentials.token()) {
-        Ok(claims) => {
-            req.extensions_mut().insert(claims);
-            Ok(req)
-        }
+    let claims = match verify_jwt(credentials.token()) {
+        Ok(claims) => claims,
         Err(e) => {
             log::error!("JWT validation error: {}", e);
-            Err((actix_web::error::ErrorUnauthorized("Invalid token"), req))
+            return Err((actix_web::error::ErrorUnauthorized("Invalid token"), req));
         }
+    };
+
+    // Catches an account blocked after this access token was issued; the
+    // token itself stays valid until it expires, so without this a blocked
+    // user could keep calling authenticated endpoints for up to an hour.
+    let blocked = match claims.sub.parse::<Uuid>() {
+        Ok(user_id) => match req.app_data::<web::Data<redis::Client>>() {
+            Some(redis_client) => is_user_blocked(redis_client, &user_id).await.unwrap_or(false),
+            None => false,
+        },
+        Err(_) => false,
+    };
+
+    if blocked {
+        return Err((
+            actix_web::error::ErrorUnauthorized("Account is blocked"),
+            req,
+        ));
     }
+
+    req.extensions_mut().insert(claims);
+    Ok(req)
 }
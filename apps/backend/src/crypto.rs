@@ -0,0 +1,143 @@
+//! Envelope encryption for file contents at rest. Each upload gets its own
+//! random 256-bit data encryption key (DEK); the DEK itself is wrapped with a
+//! master key derived from `FILE_ENCRYPTION_KEY` so the database only ever
+//! holds ciphertext, never a usable key. Encryption is opt-in: deployments
+//! that never set `FILE_ENCRYPTION_KEY` get the pre-existing plaintext
+//! behavior, and rows uploaded before this feature existed (`enc_alg IS
+//! NULL`) keep reading back as plaintext.
+
+use aes_gcm::aead::{Aead, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use std::env;
+use subtle::ConstantTimeEq;
+
+/// The only algorithm this module currently speaks; stored alongside each
+/// encrypted row so a future algorithm change doesn't strand old rows.
+pub const ALG_AES_256_GCM: &str = "aes-256-gcm";
+
+const NONCE_LEN: usize = 12;
+const DEK_LEN: usize = 32;
+
+#[derive(Debug)]
+pub enum CryptoError {
+    /// `FILE_ENCRYPTION_KEY` isn't set; encryption was requested anyway.
+    NotConfigured,
+    /// `enc_alg` on the row isn't one this build knows how to decrypt.
+    UnsupportedAlgorithm(String),
+    /// AEAD authentication failed — wrong key, corrupted ciphertext, or a
+    /// malformed envelope. Always fails closed: no partial plaintext is ever
+    /// returned.
+    AuthenticationFailed,
+}
+
+impl std::fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CryptoError::NotConfigured => write!(f, "file encryption is not configured"),
+            CryptoError::UnsupportedAlgorithm(alg) => {
+                write!(f, "unsupported encryption algorithm: {}", alg)
+            }
+            CryptoError::AuthenticationFailed => {
+                write!(f, "ciphertext failed authentication")
+            }
+        }
+    }
+}
+
+impl std::error::Error for CryptoError {}
+
+/// The wrapped-DEK, nonce, and algorithm tag persisted on a `files` row.
+/// `enc_alg: None` means the row is plaintext (it predates this feature or
+/// was uploaded without `FILE_ENCRYPTION_KEY` set) and `minio_path` holds
+/// plaintext bytes.
+pub struct Envelope {
+    pub wrapped_key: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub alg: &'static str,
+}
+
+/// Whether this deployment has a master key configured at all; callers use
+/// this to decide whether new uploads should be encrypted.
+pub fn encryption_enabled() -> bool {
+    env::var("FILE_ENCRYPTION_KEY").is_ok()
+}
+
+/// Stretches `FILE_ENCRYPTION_KEY` into a 256-bit key via SHA-256, so
+/// operators can set a passphrase of any length rather than a raw 32-byte
+/// secret.
+fn master_key() -> Result<Key<Aes256Gcm>, CryptoError> {
+    let secret = env::var("FILE_ENCRYPTION_KEY").map_err(|_| CryptoError::NotConfigured)?;
+    let digest = Sha256::digest(secret.as_bytes());
+    Ok(*Key::<Aes256Gcm>::from_slice(&digest))
+}
+
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut buf = [0u8; N];
+    OsRng.fill_bytes(&mut buf);
+    buf
+}
+
+/// Generates a fresh DEK, encrypts `plaintext` with it, wraps the DEK with
+/// the master key, and returns the ciphertext alongside the envelope needed
+/// to decrypt it later.
+pub fn encrypt(plaintext: &[u8]) -> Result<(Vec<u8>, Envelope), CryptoError> {
+    let master = master_key()?;
+    let master_cipher = Aes256Gcm::new(&master);
+
+    let dek_bytes = random_bytes::<DEK_LEN>();
+    let dek = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&dek_bytes));
+
+    let nonce_bytes = random_bytes::<NONCE_LEN>();
+    let nonce = Nonce::from_slice(&nonce_bytes);
+    let ciphertext = dek
+        .encrypt(nonce, plaintext)
+        .map_err(|_| CryptoError::AuthenticationFailed)?;
+
+    let wrap_nonce_bytes = random_bytes::<NONCE_LEN>();
+    let wrap_nonce = Nonce::from_slice(&wrap_nonce_bytes);
+    let wrapped_dek = master_cipher
+        .encrypt(wrap_nonce, dek_bytes.as_slice())
+        .map_err(|_| CryptoError::AuthenticationFailed)?;
+
+    // Store the wrap nonce alongside the wrapped key since it's needed to
+    // unwrap the DEK again; the data nonce is kept separately in its own
+    // column since the handler needs it without touching the key material.
+    let mut wrapped_key = Vec::with_capacity(NONCE_LEN + wrapped_dek.len());
+    wrapped_key.extend_from_slice(&wrap_nonce_bytes);
+    wrapped_key.extend_from_slice(&wrapped_dek);
+
+    Ok((
+        ciphertext,
+        Envelope {
+            wrapped_key,
+            nonce: nonce_bytes.to_vec(),
+            alg: ALG_AES_256_GCM,
+        },
+    ))
+}
+
+/// Unwraps the DEK with the master key and decrypts `ciphertext`. Fails
+/// closed on any authentication failure, malformed envelope, or unsupported
+/// `alg` — never returns partially-decrypted data.
+pub fn decrypt(ciphertext: &[u8], wrapped_key: &[u8], nonce: &[u8], alg: &str) -> Result<Vec<u8>, CryptoError> {
+    if !bool::from(alg.as_bytes().ct_eq(ALG_AES_256_GCM.as_bytes())) {
+        return Err(CryptoError::UnsupportedAlgorithm(alg.to_string()));
+    }
+    if wrapped_key.len() < NONCE_LEN {
+        return Err(CryptoError::AuthenticationFailed);
+    }
+
+    let master = master_key()?;
+    let master_cipher = Aes256Gcm::new(&master);
+
+    let (wrap_nonce_bytes, wrapped_dek) = wrapped_key.split_at(NONCE_LEN);
+    let dek_bytes = master_cipher
+        .decrypt(Nonce::from_slice(wrap_nonce_bytes), wrapped_dek)
+        .map_err(|_| CryptoError::AuthenticationFailed)?;
+
+    let dek = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&dek_bytes));
+    dek.decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| CryptoError::AuthenticationFailed)
+}
@@ -1,5 +1,7 @@
-use crate::models::{File, Memo, MemoAttachment, MemoMessage, User};
+use crate::models::{File, Memo, MemoAttachment, MemoMessage, MemoPermission, MemoShare, User};
+use crate::permissions::PermissionType;
 use sqlx::{Pool, Postgres, Row};
+use std::collections::HashMap;
 use uuid::Uuid;
 
 pub async fn create_user(
@@ -30,6 +32,22 @@ pub async fn get_user_by_email(
     Ok(user)
 }
 
+/// Finds the account for `email`, or creates one with `unusable_password_hash`
+/// — for passwordless sign-in paths (OAuth, magic links) where there's no
+/// password to check at login time, the caller hashes a throwaway secret so
+/// the account still satisfies the `NOT NULL` column.
+pub async fn get_or_create_user_by_email(
+    pool: &Pool<Postgres>,
+    email: &str,
+    unusable_password_hash: &str,
+) -> Result<User, sqlx::Error> {
+    if let Some(user) = get_user_by_email(pool, email).await? {
+        return Ok(user);
+    }
+
+    create_user(pool, email, unusable_password_hash).await
+}
+
 pub async fn get_user_by_id(
     pool: &Pool<Postgres>,
     user_id: &Uuid,
@@ -42,6 +60,28 @@ pub async fn get_user_by_id(
     Ok(user)
 }
 
+pub async fn set_user_blocked(
+    pool: &Pool<Postgres>,
+    user_id: &Uuid,
+    is_blocked: bool,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE users SET is_blocked = $1 WHERE id = $2")
+        .bind(is_blocked)
+        .bind(user_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Envelope fields for an encrypted upload; `None` leaves `enc_alg` (and
+/// therefore the row) as plaintext, matching the pre-encryption schema.
+pub struct FileEncryption<'a> {
+    pub wrapped_key: &'a [u8],
+    pub nonce: &'a [u8],
+    pub alg: &'a str,
+}
+
 pub async fn create_file(
     pool: &Pool<Postgres>,
     user_id: &Uuid,
@@ -49,34 +89,92 @@ pub async fn create_file(
     minio_path: &str,
     file_size: i64,
     mime_type: Option<&str>,
+    encryption: Option<FileEncryption<'_>>,
 ) -> Result<File, sqlx::Error> {
     let file = sqlx::query_as::<_, File>(
-        "INSERT INTO files (user_id, filename, minio_path, file_size, mime_type, status)
-         VALUES ($1, $2, $3, $4, $5, 'uploaded') RETURNING *",
+        "INSERT INTO files (user_id, filename, minio_path, file_size, mime_type, status, enc_key, enc_nonce, enc_alg)
+         VALUES ($1, $2, $3, $4, $5, 'processing', $6, $7, $8) RETURNING *",
     )
     .bind(user_id)
     .bind(filename)
     .bind(minio_path)
     .bind(file_size)
     .bind(mime_type)
+    .bind(encryption.as_ref().map(|e| e.wrapped_key))
+    .bind(encryption.as_ref().map(|e| e.nonce))
+    .bind(encryption.as_ref().map(|e| e.alg))
     .fetch_one(pool)
     .await?;
 
     Ok(file)
 }
 
+/// Optional narrowing applied to a user's file listing; each field is an
+/// exact match except `filename`, which is a case-insensitive substring.
+#[derive(Debug, Default)]
+pub struct FileListFilters<'a> {
+    pub mime_type: Option<&'a str>,
+    pub status: Option<&'a str>,
+    pub filename: Option<&'a str>,
+}
+
+fn push_file_filters<'a>(
+    qb: &mut sqlx::QueryBuilder<'a, Postgres>,
+    user_id: &'a Uuid,
+    filters: &FileListFilters<'a>,
+) {
+    qb.push(" WHERE user_id = ").push_bind(user_id);
+    if let Some(mime_type) = filters.mime_type {
+        qb.push(" AND mime_type = ").push_bind(mime_type);
+    }
+    if let Some(status) = filters.status {
+        qb.push(" AND status = ").push_bind(status);
+    }
+    if let Some(filename) = filters.filename {
+        qb.push(" AND filename ILIKE ")
+            .push_bind(format!("%{}%", filename));
+    }
+}
+
+/// Keyset-paginated file listing, newest first. `cursor` is the
+/// `(created_at, id)` of the last row the caller saw; rows are returned
+/// strictly after it in sort order so pages don't skip or repeat rows when
+/// new files land between requests.
 pub async fn get_user_files(
     pool: &Pool<Postgres>,
     user_id: &Uuid,
+    limit: i64,
+    cursor: Option<(chrono::NaiveDateTime, Uuid)>,
+    filters: &FileListFilters<'_>,
 ) -> Result<Vec<File>, sqlx::Error> {
-    let files = sqlx::query_as::<_, File>(
-        "SELECT * FROM files WHERE user_id = $1 ORDER BY created_at DESC",
-    )
-    .bind(user_id)
-    .fetch_all(pool)
-    .await?;
+    let mut qb = sqlx::QueryBuilder::new("SELECT * FROM files");
+    push_file_filters(&mut qb, user_id, filters);
+
+    if let Some((created_at, id)) = cursor {
+        qb.push(" AND (created_at, id) < (")
+            .push_bind(created_at)
+            .push(", ")
+            .push_bind(id)
+            .push(")");
+    }
+
+    qb.push(" ORDER BY created_at DESC, id DESC LIMIT ")
+        .push_bind(limit);
+
+    qb.build_query_as::<File>().fetch_all(pool).await
+}
+
+/// Total rows matching `filters`, ignoring pagination — used to populate
+/// `FileListResponse.total` alongside the current page.
+pub async fn count_user_files(
+    pool: &Pool<Postgres>,
+    user_id: &Uuid,
+    filters: &FileListFilters<'_>,
+) -> Result<i64, sqlx::Error> {
+    let mut qb = sqlx::QueryBuilder::new("SELECT COUNT(*) FROM files");
+    push_file_filters(&mut qb, user_id, filters);
 
-    Ok(files)
+    qb.build_query_scalar().fetch_one(pool).await
 }
 
 pub async fn get_file_by_id(
@@ -93,6 +191,82 @@ pub async fn get_file_by_id(
     Ok(file)
 }
 
+pub async fn update_file_status(
+    pool: &Pool<Postgres>,
+    file_id: &Uuid,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE files SET status = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+        .bind(status)
+        .bind(file_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+pub async fn set_file_previews(
+    pool: &Pool<Postgres>,
+    file_id: &Uuid,
+    previews: &serde_json::Value,
+) -> Result<(), sqlx::Error> {
+    sqlx::query("UPDATE files SET previews = $1, updated_at = CURRENT_TIMESTAMP WHERE id = $2")
+        .bind(previews)
+        .bind(file_id)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Atomically registers a reference to `hash`: inserts a fresh `blobs` row
+/// with `ref_count = 1`, or bumps `ref_count` on the existing one. A plain
+/// check-then-insert (`get_blob` then a bare `INSERT`) races two concurrent
+/// uploads of identical content, so this relies on `ON CONFLICT` to let
+/// Postgres serialize the two. Returns whether the blob didn't exist yet
+/// (`true`), so the caller knows whether it still needs to copy the object
+/// into blob storage.
+pub async fn upsert_blob_ref(
+    pool: &Pool<Postgres>,
+    hash: &str,
+    size: i64,
+    mime_type: Option<&str>,
+) -> Result<bool, sqlx::Error> {
+    let (inserted,): (bool,) = sqlx::query_as(
+        "INSERT INTO blobs (hash, size, mime_type, ref_count) VALUES ($1, $2, $3, 1)
+         ON CONFLICT (hash) DO UPDATE SET ref_count = blobs.ref_count + 1
+         RETURNING (xmax = 0)",
+    )
+    .bind(hash)
+    .bind(size)
+    .bind(mime_type)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(inserted)
+}
+
+/// Decrements the blob's refcount and deletes its row once nothing
+/// references it; returns the refcount after the decrement so the caller
+/// knows whether to also remove the underlying MinIO object.
+pub async fn decrement_blob_ref_count(pool: &Pool<Postgres>, hash: &str) -> Result<i64, sqlx::Error> {
+    let (ref_count,): (i64,) = sqlx::query_as(
+        "UPDATE blobs SET ref_count = ref_count - 1 WHERE hash = $1 RETURNING ref_count",
+    )
+    .bind(hash)
+    .fetch_one(pool)
+    .await?;
+
+    if ref_count <= 0 {
+        sqlx::query("DELETE FROM blobs WHERE hash = $1 AND ref_count <= 0")
+            .bind(hash)
+            .execute(pool)
+            .await?;
+    }
+
+    Ok(ref_count)
+}
+
 pub async fn delete_file(
     pool: &Pool<Postgres>,
     file_id: &Uuid,
@@ -178,20 +352,164 @@ pub async fn get_user_memos(
     Ok(memos)
 }
 
+/// Fetches a memo the user can see at all — owned outright, or shared with
+/// them at any permission level. Callers that need to distinguish Read from
+/// Write/Manage should follow up with `resolve_memo_permission`.
 pub async fn get_memo_by_id(
     pool: &Pool<Postgres>,
     memo_id: &Uuid,
     user_id: &Uuid,
 ) -> Result<Option<Memo>, sqlx::Error> {
-    let memo = sqlx::query_as::<_, Memo>("SELECT * FROM memos WHERE id = $1 AND user_id = $2")
+    let memo = sqlx::query_as::<_, Memo>(
+        "SELECT m.* FROM memos m
+         WHERE m.id = $1
+           AND (m.user_id = $2 OR EXISTS (
+               SELECT 1 FROM memo_permissions mp WHERE mp.memo_id = m.id AND mp.user_id = $2
+           ))",
+    )
+    .bind(memo_id)
+    .bind(user_id)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(memo)
+}
+
+/// Resolves `user_id`'s effective `PermissionType` on `memo_id`: the owner
+/// always gets `Manage`, everyone else gets whatever `memo_permissions`
+/// says (or `NoPermission` if there's no row at all).
+pub async fn resolve_memo_permission(
+    pool: &Pool<Postgres>,
+    memo_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<PermissionType, sqlx::Error> {
+    let is_owner: Option<(Uuid,)> =
+        sqlx::query_as("SELECT id FROM memos WHERE id = $1 AND user_id = $2")
+            .bind(memo_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    if is_owner.is_some() {
+        return Ok(PermissionType::Manage);
+    }
+
+    let row: Option<(String,)> =
+        sqlx::query_as("SELECT permission FROM memo_permissions WHERE memo_id = $1 AND user_id = $2")
+            .bind(memo_id)
+            .bind(user_id)
+            .fetch_optional(pool)
+            .await?;
+
+    Ok(row
+        .map(|(permission,)| PermissionType::parse(&permission))
+        .unwrap_or(PermissionType::NoPermission))
+}
+
+/// Grants or updates `user_id`'s access to `memo_id`.
+pub async fn upsert_memo_permission(
+    pool: &Pool<Postgres>,
+    memo_id: &Uuid,
+    user_id: &Uuid,
+    permission: PermissionType,
+) -> Result<MemoPermission, sqlx::Error> {
+    let granted = sqlx::query_as::<_, MemoPermission>(
+        "INSERT INTO memo_permissions (memo_id, user_id, permission) VALUES ($1, $2, $3)
+         ON CONFLICT (memo_id, user_id) DO UPDATE SET permission = EXCLUDED.permission
+         RETURNING *",
+    )
+    .bind(memo_id)
+    .bind(user_id)
+    .bind(permission.as_str())
+    .fetch_one(pool)
+    .await?;
+
+    Ok(granted)
+}
+
+/// Revokes `user_id`'s share on `memo_id`; returns `false` if they had none.
+pub async fn revoke_memo_permission(
+    pool: &Pool<Postgres>,
+    memo_id: &Uuid,
+    user_id: &Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM memo_permissions WHERE memo_id = $1 AND user_id = $2")
         .bind(memo_id)
         .bind(user_id)
-        .fetch_optional(pool)
+        .execute(pool)
         .await?;
 
+    Ok(result.rows_affected() > 0)
+}
+
+/// Publishes `memo_id` under `slug` for unauthenticated read-only access.
+/// Replaces any existing publish for this memo, so a memo only ever has
+/// one live share link at a time.
+pub async fn publish_memo(
+    pool: &Pool<Postgres>,
+    memo_id: &Uuid,
+    slug: &str,
+    expires_at: Option<chrono::NaiveDateTime>,
+) -> Result<MemoShare, sqlx::Error> {
+    let share = sqlx::query_as::<_, MemoShare>(
+        "INSERT INTO memo_shares (memo_id, slug, expires_at) VALUES ($1, $2, $3)
+         ON CONFLICT (memo_id) DO UPDATE SET slug = EXCLUDED.slug, expires_at = EXCLUDED.expires_at
+         RETURNING *",
+    )
+    .bind(memo_id)
+    .bind(slug)
+    .bind(expires_at)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(share)
+}
+
+/// Revokes `memo_id`'s share link, if any; returns `false` if it wasn't published.
+pub async fn revoke_memo_publish(
+    pool: &Pool<Postgres>,
+    memo_id: &Uuid,
+) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM memo_shares WHERE memo_id = $1")
+        .bind(memo_id)
+        .execute(pool)
+        .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Resolves a live (unexpired) share link to its memo, bypassing the
+/// `user_id` ownership filter `get_memo_by_id` enforces — this is the one
+/// place memo content is meant to be readable without authentication.
+pub async fn get_memo_by_slug(pool: &Pool<Postgres>, slug: &str) -> Result<Option<Memo>, sqlx::Error> {
+    let memo = sqlx::query_as::<_, Memo>(
+        "SELECT m.* FROM memos m
+         JOIN memo_shares ms ON ms.memo_id = m.id
+         WHERE ms.slug = $1 AND (ms.expires_at IS NULL OR ms.expires_at > CURRENT_TIMESTAMP)",
+    )
+    .bind(slug)
+    .fetch_optional(pool)
+    .await?;
+
     Ok(memo)
 }
 
+/// Messages for a publicly shared memo, with no `user_id` check — callers
+/// must have already resolved the memo through `get_memo_by_slug`.
+pub async fn get_shared_memo_messages(
+    pool: &Pool<Postgres>,
+    memo_id: &Uuid,
+) -> Result<Vec<MemoMessage>, sqlx::Error> {
+    let messages = sqlx::query_as::<_, MemoMessage>(
+        "SELECT * FROM memo_messages WHERE memo_id = $1 ORDER BY created_at ASC",
+    )
+    .bind(memo_id)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(messages)
+}
+
 #[allow(dead_code)]
 pub async fn update_memo(
     pool: &Pool<Postgres>,
@@ -214,14 +532,12 @@ pub async fn update_memo(
     Ok(memo)
 }
 
-pub async fn delete_memo(
-    pool: &Pool<Postgres>,
-    memo_id: &Uuid,
-    user_id: &Uuid,
-) -> Result<bool, sqlx::Error> {
-    let result = sqlx::query("DELETE FROM memos WHERE id = $1 AND user_id = $2")
+/// Deletes a memo outright. Callers are expected to have already checked
+/// `resolve_memo_permission(...).can_manage()` — this cascades to the
+/// memo's permissions, messages, and attachments via FK constraints.
+pub async fn delete_memo(pool: &Pool<Postgres>, memo_id: &Uuid) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query("DELETE FROM memos WHERE id = $1")
         .bind(memo_id)
-        .bind(user_id)
         .execute(pool)
         .await?;
 
@@ -240,6 +556,27 @@ pub async fn get_memo_message_count(
     Ok(count.0)
 }
 
+/// Batched form of `get_memo_message_count` for list views: one grouped
+/// query instead of one `COUNT(*)` per memo. Memos with no messages are
+/// absent from the map; callers should default missing entries to `0`.
+pub async fn get_memo_message_counts(
+    pool: &Pool<Postgres>,
+    memo_ids: &[Uuid],
+) -> Result<HashMap<Uuid, i64>, sqlx::Error> {
+    if memo_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows: Vec<(Uuid, i64)> = sqlx::query_as(
+        "SELECT memo_id, COUNT(*) FROM memo_messages WHERE memo_id = ANY($1) GROUP BY memo_id",
+    )
+    .bind(memo_ids)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
 pub async fn create_memo_message(
     pool: &Pool<Postgres>,
     memo_id: &Uuid,
@@ -247,12 +584,15 @@ pub async fn create_memo_message(
     content: &str,
     role: &str,
 ) -> Result<MemoMessage, sqlx::Error> {
+    let content_html = crate::markdown::render(content);
+
     let message = sqlx::query_as::<_, MemoMessage>(
-        "INSERT INTO memo_messages (memo_id, user_id, content, role) VALUES ($1, $2, $3, $4) RETURNING *",
+        "INSERT INTO memo_messages (memo_id, user_id, content, content_html, role) VALUES ($1, $2, $3, $4, $5) RETURNING *",
     )
     .bind(memo_id)
     .bind(user_id)
     .bind(content)
+    .bind(content_html)
     .bind(role)
     .fetch_one(pool)
     .await?;
@@ -305,43 +645,85 @@ pub async fn create_memo_attachment(
     Ok(attachment)
 }
 
-pub async fn get_message_attachments(
+/// Flattened `memo_attachments` + `files` row. `ma.*, f.*` would produce two
+/// `id` and two `created_at` columns, which `Row::get("id")` can't
+/// disambiguate, so every overlapping column is pulled out under an alias
+/// instead.
+#[derive(sqlx::FromRow)]
+struct AttachmentFileRow {
+    attachment_id: Uuid,
+    message_id: Uuid,
+    file_id: Uuid,
+    attachment_created_at: chrono::NaiveDateTime,
+    user_id: Uuid,
+    filename: String,
+    minio_path: String,
+    file_size: i64,
+    mime_type: Option<String>,
+    status: String,
+    previews: Option<serde_json::Value>,
+    enc_key: Option<Vec<u8>>,
+    enc_nonce: Option<Vec<u8>>,
+    enc_alg: Option<String>,
+    file_created_at: chrono::NaiveDateTime,
+    updated_at: chrono::NaiveDateTime,
+}
+
+/// Batched form of the old per-message attachment lookup: one query joining
+/// `memo_attachments`/`files` across every message in the memo instead of
+/// one per message. Messages with no attachments are absent from the map;
+/// callers should default missing entries to an empty `Vec`.
+pub async fn get_attachments_for_messages(
     pool: &Pool<Postgres>,
-    message_id: &Uuid,
-) -> Result<Vec<(MemoAttachment, File)>, sqlx::Error> {
-    let attachments = sqlx::query(
-        "SELECT ma.*, f.* FROM memo_attachments ma
+    message_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<(MemoAttachment, File)>>, sqlx::Error> {
+    if message_ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let rows = sqlx::query_as::<_, AttachmentFileRow>(
+        "SELECT ma.id AS attachment_id, ma.message_id, ma.file_id,
+                ma.created_at AS attachment_created_at,
+                f.user_id, f.filename, f.minio_path, f.file_size, f.mime_type,
+                f.status, f.previews, f.enc_key, f.enc_nonce, f.enc_alg,
+                f.created_at AS file_created_at, f.updated_at
+         FROM memo_attachments ma
          JOIN files f ON f.id = ma.file_id
-         WHERE ma.message_id = $1
-         ORDER BY ma.created_at ASC",
+         WHERE ma.message_id = ANY($1)
+         ORDER BY ma.message_id, ma.created_at ASC",
     )
-    .bind(message_id)
+    .bind(message_ids)
     .fetch_all(pool)
     .await?;
 
-    let result = attachments
-        .into_iter()
-        .map(|row| {
-            let attachment = MemoAttachment {
-                id: row.get("id"),
-                message_id: row.get("message_id"),
-                file_id: row.get("file_id"),
-                created_at: row.get("created_at"),
-            };
-            let file = File {
-                id: row.get("id"),
-                user_id: row.get("user_id"),
-                filename: row.get("filename"),
-                minio_path: row.get("minio_path"),
-                file_size: row.get("file_size"),
-                mime_type: row.get("mime_type"),
-                status: row.get("status"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            };
-            (attachment, file)
-        })
-        .collect();
+    let mut result: HashMap<Uuid, Vec<(MemoAttachment, File)>> = HashMap::new();
+    for row in rows {
+        let attachment = MemoAttachment {
+            id: row.attachment_id,
+            message_id: row.message_id,
+            file_id: row.file_id,
+            created_at: row.attachment_created_at,
+        };
+        let file = File {
+            id: row.file_id,
+            user_id: row.user_id,
+            filename: row.filename,
+            minio_path: row.minio_path,
+            file_size: row.file_size,
+            mime_type: row.mime_type,
+            status: row.status,
+            previews: row.previews,
+            enc_key: row.enc_key,
+            enc_nonce: row.enc_nonce,
+            enc_alg: row.enc_alg,
+            created_at: row.file_created_at,
+            updated_at: row.updated_at,
+        };
+        result
+            .entry(attachment.message_id)
+            .or_default()
+            .push((attachment, file));
+    }
 
     Ok(result)
 }
@@ -0,0 +1,180 @@
+//! Pluggable embedding providers. `QdrantService` and the upload/search handlers
+//! only depend on the `Embedder` trait, so swapping models (or providers) is a
+//! matter of constructing a different implementation in `main`.
+
+use async_trait::async_trait;
+use std::env;
+use std::time::Duration;
+
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>>;
+
+    fn dimension(&self) -> usize;
+}
+
+/// HTTP client for any OpenAI-compatible `/v1/embeddings` endpoint (OpenAI itself,
+/// or a self-hosted gateway like LocalAI/vLLM that mirrors the same contract).
+pub struct OpenAiEmbedder {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    dimension: usize,
+    batch_size: usize,
+    max_retries: u32,
+}
+
+#[derive(serde::Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(serde::Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(serde::Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+impl OpenAiEmbedder {
+    pub fn from_env() -> Self {
+        let base_url = env::var("EMBEDDING_BASE_URL")
+            .unwrap_or_else(|_| "https://api.openai.com/v1".to_string());
+        let api_key = env::var("EMBEDDING_API_KEY").unwrap_or_default();
+        let model =
+            env::var("EMBEDDING_MODEL").unwrap_or_else(|_| "text-embedding-3-small".to_string());
+        let dimension = env::var("EMBEDDING_DIMENSION")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(1536);
+        let batch_size = env::var("EMBEDDING_BATCH_SIZE")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(64);
+
+        OpenAiEmbedder {
+            client: reqwest::Client::new(),
+            base_url,
+            api_key,
+            model,
+            dimension,
+            batch_size,
+            max_retries: 3,
+        }
+    }
+
+    async fn embed_chunk(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        let url = format!("{}/embeddings", self.base_url.trim_end_matches('/'));
+        let mut attempt = 0;
+
+        loop {
+            let response = self
+                .client
+                .post(&url)
+                .bearer_auth(&self.api_key)
+                .json(&EmbeddingRequest {
+                    model: &self.model,
+                    input: texts,
+                })
+                .send()
+                .await;
+
+            match response {
+                Ok(resp) if resp.status().is_success() => {
+                    let parsed: EmbeddingResponse = resp.json().await?;
+                    let mut embeddings = vec![Vec::new(); texts.len()];
+                    for item in parsed.data {
+                        if let Some(slot) = embeddings.get_mut(item.index) {
+                            *slot = item.embedding;
+                        }
+                    }
+                    return Ok(embeddings);
+                }
+                Ok(resp) if resp.status().is_server_error() && attempt < self.max_retries => {
+                    attempt += 1;
+                    let backoff = Duration::from_millis(200 * 2u64.pow(attempt));
+                    tokio::time::sleep(backoff).await;
+                }
+                Ok(resp) => {
+                    let status = resp.status();
+                    let body = resp.text().await.unwrap_or_default();
+                    return Err(format!("embedding request failed ({}): {}", status, body).into());
+                }
+                Err(e) if attempt < self.max_retries => {
+                    attempt += 1;
+                    let backoff = Duration::from_millis(200 * 2u64.pow(attempt));
+                    tokio::time::sleep(backoff).await;
+                    log::warn!("embedding request error (retry {}): {}", attempt, e);
+                }
+                Err(e) => return Err(Box::new(e)),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenAiEmbedder {
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        let mut results = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(self.batch_size.max(1)) {
+            let embeddings = self.embed_chunk(chunk).await?;
+            results.extend(embeddings);
+        }
+        Ok(results)
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+}
+
+/// Deterministic stand-in used in tests so RAG flows can be exercised without a
+/// live embedding API.
+#[cfg(feature = "mock-embeddings")]
+pub struct MockEmbedder {
+    dimension: usize,
+}
+
+#[cfg(feature = "mock-embeddings")]
+impl MockEmbedder {
+    pub fn new(dimension: usize) -> Self {
+        MockEmbedder { dimension }
+    }
+}
+
+#[cfg(feature = "mock-embeddings")]
+impl Default for MockEmbedder {
+    fn default() -> Self {
+        MockEmbedder::new(1536)
+    }
+}
+
+#[cfg(feature = "mock-embeddings")]
+#[async_trait]
+impl Embedder for MockEmbedder {
+    async fn embed_batch(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<Vec<f32>>, Box<dyn std::error::Error>> {
+        Ok(texts.iter().map(|_| vec![0.1; self.dimension]).collect())
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+}
@@ -0,0 +1,148 @@
+//! Centralizes HTTP error mapping. Handlers used to each hand-roll the same
+//! `.map_err(|e| { log::error!(...); ErrorInternalServerError(...) })`
+//! chain, which both duplicated the logging and produced inconsistent
+//! bodies (some handlers returned `{"error": ...}` JSON, others the plain
+//! text actix_web's `ErrorXxx` helpers emit). Handlers return
+//! `Result<HttpResponse, AppError>` instead, use `?`, and get a single JSON
+//! shape and the right status code for free via `ResponseError`.
+
+use crate::models::ErrorResponse;
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum AppError {
+    /// A path/claims UUID failed to parse.
+    InvalidId,
+    /// The request body or a query param is malformed in some other way.
+    BadRequest(String),
+    /// The resource doesn't exist, or the caller has no access to it —
+    /// deliberately not distinguished from the caller's point of view.
+    NotFound(String),
+    /// No valid credentials/session.
+    Unauthorized(String),
+    /// Authenticated, but lacking the permission this action requires.
+    Forbidden(String),
+    /// A query failed; the underlying `sqlx::Error` is logged, never
+    /// returned to the client.
+    Database(sqlx::Error),
+    /// An object-storage (MinIO) operation failed.
+    Storage(String),
+    /// A unique-constraint violation, e.g. registering an email that's
+    /// already taken.
+    Conflict(String),
+    /// Anything else unexpected; logged with its message before a generic
+    /// 500 goes out.
+    Internal(String),
+}
+
+impl AppError {
+    pub fn not_found() -> Self {
+        AppError::NotFound("Not found".to_string())
+    }
+
+    pub fn unauthorized() -> Self {
+        AppError::Unauthorized("Unauthorized".to_string())
+    }
+}
+
+impl fmt::Display for AppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppError::InvalidId => write!(f, "invalid id"),
+            AppError::BadRequest(msg) => write!(f, "bad request: {}", msg),
+            AppError::NotFound(msg) => write!(f, "not found: {}", msg),
+            AppError::Unauthorized(msg) => write!(f, "unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "forbidden: {}", msg),
+            AppError::Database(e) => write!(f, "database error: {}", e),
+            AppError::Storage(msg) => write!(f, "storage error: {}", msg),
+            AppError::Conflict(msg) => write!(f, "conflict: {}", msg),
+            AppError::Internal(msg) => write!(f, "internal error: {}", msg),
+        }
+    }
+}
+
+impl ResponseError for AppError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::InvalidId => StatusCode::BAD_REQUEST,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Database(_) | AppError::Storage(_) | AppError::Internal(_) => {
+                StatusCode::INTERNAL_SERVER_ERROR
+            }
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let message = match self {
+            AppError::InvalidId => "Invalid ID".to_string(),
+            AppError::BadRequest(msg) => msg.clone(),
+            AppError::NotFound(msg) => msg.clone(),
+            AppError::Unauthorized(msg) => msg.clone(),
+            AppError::Forbidden(msg) => msg.clone(),
+            AppError::Conflict(msg) => msg.clone(),
+            AppError::Database(_) | AppError::Storage(_) | AppError::Internal(_) => {
+                handle_error(self);
+                "Internal server error".to_string()
+            }
+        };
+
+        HttpResponse::build(self.status_code()).json(ErrorResponse { error: message })
+    }
+}
+
+/// Logs the real cause server-side for the variants whose client-facing
+/// message is intentionally generic, so nothing internal leaks in the body.
+fn handle_error(error: &AppError) {
+    log::error!("{}", error);
+}
+
+impl From<sqlx::Error> for AppError {
+    fn from(e: sqlx::Error) -> Self {
+        // Postgres unique_violation: surface as a 409 instead of a generic
+        // 500, so e.g. `register` can rely on `?` instead of a pre-check
+        // SELECT that's racy anyway.
+        if let sqlx::Error::Database(ref db_err) = e {
+            if db_err.code().as_deref() == Some("23505") {
+                let message = match db_err.constraint() {
+                    Some(c) if c.contains("email") => "User already exists".to_string(),
+                    _ => "Resource already exists".to_string(),
+                };
+                return AppError::Conflict(message);
+            }
+        }
+        AppError::Database(e)
+    }
+}
+
+impl From<uuid::Error> for AppError {
+    fn from(_: uuid::Error) -> Self {
+        AppError::InvalidId
+    }
+}
+
+impl From<redis::RedisError> for AppError {
+    fn from(e: redis::RedisError) -> Self {
+        AppError::Internal(format!("redis error: {}", e))
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for AppError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        AppError::Internal(format!("jwt error: {}", e))
+    }
+}
+
+/// Covers the auth/oauth Redis helpers, which box together
+/// `redis::RedisError` with the odd `uuid`/`serde_json` parse error under
+/// one signature; losing the concrete type here is fine since by the time
+/// one of those helpers fails, the caller only needs a 500.
+impl From<Box<dyn std::error::Error>> for AppError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        AppError::Internal(e.to_string())
+    }
+}
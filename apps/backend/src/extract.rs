@@ -0,0 +1,125 @@
+//! Plain-text extraction for RAG indexing. `ingestion::process_job` used to
+//! hand raw bytes straight to `chunk_text` via `from_utf8_lossy`, which turns
+//! PDFs, docx/xlsx, and anything else non-UTF8 into binary garbage chunks.
+//! This dispatches on the file's (sniffed) MIME type to pull out real text,
+//! and reports `None` when a format has nothing worth indexing.
+//!
+//! The stored MIME type for a docx/xlsx upload is the Office-specific one,
+//! not the generic `application/zip` its zip signature alone would sniff
+//! as — see `validate::validate_upload` — so the matches below actually
+//! get hit instead of silently falling through to `None`.
+
+use std::io::Read;
+
+/// Extracts indexable plain text from `file_data` for the given MIME type.
+/// Returns `None` when the format isn't supported or yields no text, in
+/// which case the caller should store the file without vector indexing.
+pub fn extract_text(mime_type: &str, file_data: &[u8]) -> Option<String> {
+    match mime_type {
+        "application/pdf" => extract_pdf(file_data),
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => {
+            extract_docx(file_data)
+        }
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet" => {
+            extract_xlsx(file_data)
+        }
+        "text/html" => extract_html(file_data),
+        "text/plain" | "text/markdown" | "text/csv" => extract_plain(file_data),
+        _ => None,
+    }
+}
+
+fn extract_plain(file_data: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(file_data).into_owned();
+    non_empty(text)
+}
+
+fn extract_pdf(file_data: &[u8]) -> Option<String> {
+    let text = pdf_extract::extract_text_from_mem(file_data).ok()?;
+    non_empty(text)
+}
+
+/// A docx is a zip archive; its text body lives in `word/document.xml` as a
+/// run of `<w:t>` text elements separated by paragraph/run markup.
+fn extract_docx(file_data: &[u8]) -> Option<String> {
+    let xml = read_zip_entry(file_data, "word/document.xml")?;
+    non_empty(strip_tags(&xml))
+}
+
+/// An xlsx is a zip archive too; cell text is spread across every
+/// `xl/worksheets/sheetN.xml`, with shared strings interned in
+/// `xl/sharedStrings.xml`. The text of every sheet and of the shared-string
+/// table is concatenated as is; shared strings are not mapped back to their
+/// cells (that needs cell-by-cell parsing). Good enough for RAG recall over a
+/// spreadsheet's visible text.
+fn extract_xlsx(file_data: &[u8]) -> Option<String> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(file_data)).ok()?;
+
+    let mut combined = String::new();
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).ok()?;
+        let name = entry.name().to_string();
+        if !name.starts_with("xl/worksheets/") && name != "xl/sharedStrings.xml" {
+            continue;
+        }
+
+        let mut xml = String::new();
+        if entry.read_to_string(&mut xml).is_err() {
+            continue;
+        }
+        combined.push_str(&strip_tags(&xml));
+        combined.push('\n');
+    }
+
+    non_empty(combined)
+}
+
+fn extract_html(file_data: &[u8]) -> Option<String> {
+    let html = String::from_utf8_lossy(file_data);
+    non_empty(strip_tags(&html))
+}
+
+fn read_zip_entry(file_data: &[u8], entry_name: &str) -> Option<String> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(file_data)).ok()?;
+    let mut entry = archive.by_name(entry_name).ok()?;
+    let mut xml = String::new();
+    entry.read_to_string(&mut xml).ok()?;
+    Some(xml)
+}
+
+/// Strips markup tags and collapses entity-decoded whitespace, leaving just
+/// the visible text content. Not a real parser — no script/style skipping,
+/// no entity table beyond the handful that show up in practice — but it's
+/// enough to turn markup into something an embedder can usefully chunk.
+fn strip_tags(markup: &str) -> String {
+    let mut text = String::with_capacity(markup.len());
+    let mut in_tag = false;
+    for c in markup.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+
+    text.replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn non_empty(text: String) -> Option<String> {
+    if text.trim().is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
@@ -1,19 +1,51 @@
+use crate::crypto;
 use crate::db;
+use crate::db::FileListFilters;
+use crate::errors::AppError;
+use crate::ingestion::{self, IngestionJob};
 use crate::minio_service::MinioClient;
 use crate::models::Claims;
-use crate::models::FileResponse;
-use crate::qdrant_service::{create_mock_embedding, QdrantService};
+use crate::models::{DownloadUrlResponse, FileListResponse, FileResponse, ListFilesQuery};
+use crate::qdrant_service::QdrantService;
 use actix_multipart::Multipart;
-use actix_web::{web, Error, HttpResponse};
+use actix_web::{web, HttpResponse};
+use base64::Engine;
 use futures_util::StreamExt;
+use sha2::{Digest, Sha256};
 use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
 use uuid::Uuid;
 
+/// Default ceiling on a single upload's size when `MAX_UPLOAD_SIZE_BYTES`
+/// isn't set. Enforced mid-stream, not after the whole body has landed.
+const DEFAULT_MAX_UPLOAD_BYTES: usize = 500 * 1024 * 1024;
+
+/// Default/max page size for `GET /api/files` when the caller doesn't ask
+/// for a specific `limit`, or asks for more than this.
+const DEFAULT_FILES_PAGE_SIZE: i64 = 50;
+const MAX_FILES_PAGE_SIZE: i64 = 200;
+
+/// Leading bytes captured per upload for magic-number sniffing; every
+/// signature `validate::sniff_mime_type` checks for fits well inside this.
+const MIME_SNIFF_HEADER_BYTES: usize = 32;
+
+/// How long a presigned `download-url` link stays valid.
+const DOWNLOAD_URL_TTL_SECONDS: u32 = 300;
+
+fn max_upload_bytes() -> usize {
+    env::var("MAX_UPLOAD_SIZE_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_MAX_UPLOAD_BYTES)
+}
+
 #[utoipa::path(
     post,
     path = "/api/files/upload",
     responses(
-        (status = 200, description = "File uploaded successfully", body = FileResponse),
+        (status = 200, description = "File accepted and queued for processing", body = FileResponse),
         (status = 401, description = "Unauthorized")
     ),
     security(
@@ -23,97 +55,180 @@ use uuid::Uuid;
 pub async fn upload_file(
     pool: web::Data<Pool<Postgres>>,
     minio: web::Data<MinioClient>,
-    qdrant: web::Data<QdrantService>,
+    redis_client: web::Data<redis::Client>,
     claims: web::ReqData<Claims>,
     mut payload: Multipart,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
     let mut filename = String::new();
-    let mut file_data = Vec::new();
+    let mut staged = None;
+    let mut file_size: i64 = 0;
 
     while let Some(item) = payload.next().await {
-        let mut field = item.map_err(|e| {
-            log::error!("Multipart error: {}", e);
-            actix_web::error::ErrorBadRequest("Invalid multipart data")
-        })?;
+        let mut field =
+            item.map_err(|e| AppError::BadRequest(format!("Invalid multipart data: {}", e)))?;
 
         let content_disposition = field.content_disposition();
         if let Some(name) = content_disposition.get_filename() {
             filename = name.to_string();
         }
 
-        while let Some(chunk) = field.next().await {
-            let data = chunk.map_err(|e| {
-                log::error!("Chunk error: {}", e);
-                actix_web::error::ErrorBadRequest("Invalid chunk data")
-            })?;
-            file_data.extend_from_slice(&data);
+        if filename.is_empty() {
+            continue;
         }
-    }
-
-    if filename.is_empty() || file_data.is_empty() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "No file uploaded"
-        })));
-    }
 
-    if let Err(e) = qdrant.ensure_collection_exists(&user_id).await {
-        log::error!("Qdrant error: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Vector DB error"
-        })));
+        // Stream this field straight to MinIO instead of buffering the whole
+        // file in memory, hashing each chunk as it passes through so we know
+        // the content-addressed key once the upload completes. `put_object_stream`
+        // already batches writes into multi-megabyte S3 multipart parts, so we
+        // only need to enforce the size cap here, bailing out mid-stream
+        // instead of after the whole body has been read.
+        let hasher = Arc::new(Mutex::new(Sha256::new()));
+        let hasher_for_stream = hasher.clone();
+        let bytes_seen = Arc::new(Mutex::new(0usize));
+        let bytes_seen_for_stream = bytes_seen.clone();
+        let size_exceeded = Arc::new(Mutex::new(false));
+        let size_exceeded_for_stream = size_exceeded.clone();
+        let max_bytes = max_upload_bytes();
+        // Captures just enough leading bytes to sniff the real content type
+        // once the stream finishes; magic numbers live well within this.
+        let header = Arc::new(Mutex::new(Vec::with_capacity(MIME_SNIFF_HEADER_BYTES)));
+        let header_for_stream = header.clone();
+        let stream = field.map(move |chunk| {
+            let b = chunk.map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+
+            let mut seen = bytes_seen_for_stream.lock().unwrap();
+            *seen += b.len();
+            if *seen > max_bytes {
+                *size_exceeded_for_stream.lock().unwrap() = true;
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Other,
+                    "upload exceeds maximum size",
+                ));
+            }
+
+            let mut header = header_for_stream.lock().unwrap();
+            if header.len() < MIME_SNIFF_HEADER_BYTES {
+                let take = (MIME_SNIFF_HEADER_BYTES - header.len()).min(b.len());
+                header.extend_from_slice(&b[..take]);
+            }
+            drop(header);
+
+            hasher_for_stream.lock().unwrap().update(&b);
+            Ok(b.to_vec())
+        });
+
+        let staging_path = format!("staging/{}", Uuid::new_v4());
+        let size = match minio.upload_stream_raw(&staging_path, stream).await {
+            Ok(size) => size,
+            Err(e) => {
+                minio.delete_file(&staging_path).await.ok();
+                if *size_exceeded.lock().unwrap() {
+                    return Ok(HttpResponse::PayloadTooLarge().json(serde_json::json!({
+                        "error": "File exceeds maximum upload size"
+                    })));
+                }
+                log::error!("MinIO error: {}", e);
+                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "Storage error"
+                })));
+            }
+        };
+
+        let hash = hex::encode(hasher.lock().unwrap().clone().finalize());
+        staged = Some((staging_path, hash, header.lock().unwrap().clone()));
+        file_size = size as i64;
     }
 
-    let minio_path = match minio.upload_file(&user_id, &filename, &file_data).await {
-        Ok(path) => path,
-        Err(e) => {
-            log::error!("MinIO error: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Storage error"
+    let (staging_path, hash, header) = match staged {
+        Some(staged) => staged,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "No file uploaded"
             })));
         }
     };
 
-    let file_size = file_data.len() as i64;
-    let mime_type = Some(guess_mime_type(&filename));
-
-    let file = match db::create_file(
-        &pool,
-        &user_id,
-        &filename,
-        &minio_path,
-        file_size,
-        mime_type,
-    )
-    .await
-    {
-        Ok(f) => f,
+    let claimed_mime_type = guess_mime_type(&filename);
+    let sniffed_mime_type = match crate::validate::validate_upload(&header, claimed_mime_type) {
+        Ok(mime_type) => mime_type,
         Err(e) => {
-            log::error!("Database error: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
+            minio.delete_file(&staging_path).await.ok();
+            return Ok(HttpResponse::UnsupportedMediaType().json(serde_json::json!({
+                "error": e.to_string()
             })));
         }
     };
+    let mime_type = Some(sniffed_mime_type.as_str());
+
+    // Encrypted uploads get their own object keyed by file id rather than by
+    // content hash: the ciphertext is unique per DEK/nonce even when two
+    // uploads share identical plaintext, so it can't be content-addressed or
+    // ref-counted like the plaintext blob store below.
+    let file = if crypto::encryption_enabled() {
+        let plaintext = minio
+            .download_file(&staging_path)
+            .await
+            .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+        minio.delete_file(&staging_path).await.ok();
+
+        let (ciphertext, envelope) =
+            crypto::encrypt(&plaintext).map_err(|e| AppError::Internal(e.to_string()))?;
+
+        let object_path = format!("files/{}", Uuid::new_v4());
+        minio
+            .put_object(&object_path, &ciphertext)
+            .await
+            .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+
+        db::create_file(
+            &pool,
+            &user_id,
+            &filename,
+            &object_path,
+            file_size,
+            mime_type,
+            Some(db::FileEncryption {
+                wrapped_key: &envelope.wrapped_key,
+                nonce: &envelope.nonce,
+                alg: envelope.alg,
+            }),
+        )
+        .await?
+    } else {
+        let blob_path = blob_object_path(&hash);
+        let is_new_blob = db::upsert_blob_ref(&pool, &hash, file_size, mime_type).await?;
+        if is_new_blob {
+            minio
+                .copy_object(&staging_path, &blob_path)
+                .await
+                .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+        }
+        minio.delete_file(&staging_path).await.ok();
+
+        db::create_file(
+            &pool,
+            &user_id,
+            &filename,
+            &blob_path,
+            file_size,
+            mime_type,
+            None,
+        )
+        .await?
+    };
 
-    let text_content = String::from_utf8_lossy(&file_data).to_string();
-    let chunks = chunk_text(&text_content, 500);
-
-    let mut embeddings = Vec::new();
-    for (idx, chunk) in chunks.iter().enumerate() {
-        let embedding = create_mock_embedding(chunk).await;
-        embeddings.push((idx, chunk.clone(), embedding));
-    }
-
-    if let Err(e) = qdrant.upsert_vectors(&user_id, &file.id, embeddings).await {
-        log::error!("Qdrant error: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Vector DB error"
-        })));
+    let job = IngestionJob {
+        user_id,
+        file_id: file.id,
+    };
+    if let Err(e) = ingestion::enqueue(&redis_client, &job).await {
+        log::error!("Redis error: {}", e);
+        db::update_file_status(&pool, &file.id, "failed").await.ok();
+        return Err(AppError::Internal(
+            "Failed to queue file for processing".to_string(),
+        ));
     }
 
     Ok(HttpResponse::Ok().json(FileResponse {
@@ -122,6 +237,8 @@ pub async fn upload_file(
         file_size: file.file_size,
         mime_type: file.mime_type,
         status: file.status,
+        previews: HashMap::new(),
+        content_hash: blob_hash_from_path(&file.minio_path).map(str::to_string),
         created_at: file.created_at,
     }))
 }
@@ -129,8 +246,10 @@ pub async fn upload_file(
 #[utoipa::path(
     get,
     path = "/api/files",
+    params(ListFilesQuery),
     responses(
-        (status = 200, description = "List of user files", body = Vec<FileResponse>),
+        (status = 200, description = "Page of the user's files", body = FileListResponse),
+        (status = 400, description = "Malformed cursor"),
         (status = 401, description = "Unauthorized")
     ),
     security(
@@ -140,37 +259,107 @@ pub async fn upload_file(
 pub async fn list_files(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    let files = match db::get_user_files(&pool, &user_id).await {
-        Ok(files) => files,
-        Err(e) => {
-            log::error!("Database error: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
-            })));
+    query: web::Query<ListFilesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let cursor = match query.cursor.as_deref().map(decode_files_cursor) {
+        Some(Ok(cursor)) => Some(cursor),
+        Some(Err(_)) => {
+            return Err(AppError::BadRequest("Invalid cursor".to_string()));
         }
+        None => None,
     };
 
-    let response: Vec<FileResponse> = files
-        .into_iter()
-        .map(|f| FileResponse {
-            id: f.id,
-            filename: f.filename,
-            file_size: f.file_size,
-            mime_type: f.mime_type,
-            status: f.status,
-            created_at: f.created_at,
-        })
-        .collect();
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_FILES_PAGE_SIZE)
+        .clamp(1, MAX_FILES_PAGE_SIZE);
+    let filters = FileListFilters {
+        mime_type: query.mime_type.as_deref(),
+        status: query.status.as_deref(),
+        filename: query.filename.as_deref(),
+    };
+
+    // Fetch one extra row to tell whether another page follows without a
+    // separate round-trip.
+    let mut files = db::get_user_files(&pool, &user_id, limit + 1, cursor, &filters).await?;
+
+    let total = db::count_user_files(&pool, &user_id, &filters).await?;
+
+    let next_cursor = if files.len() > limit as usize {
+        files.truncate(limit as usize);
+        files
+            .last()
+            .map(|f| encode_files_cursor(f.created_at, f.id))
+    } else {
+        None
+    };
+
+    let response = FileListResponse {
+        files: files
+            .into_iter()
+            .map(|f| FileResponse {
+                id: f.id,
+                previews: preview_urls(f.id, &f.previews),
+                content_hash: blob_hash_from_path(&f.minio_path).map(str::to_string),
+                filename: f.filename,
+                file_size: f.file_size,
+                mime_type: f.mime_type,
+                status: f.status,
+                created_at: f.created_at,
+            })
+            .collect(),
+        total,
+        next_cursor,
+    };
 
     Ok(HttpResponse::Ok().json(response))
 }
 
+/// Opaque `next_cursor`/`cursor` encoding: base64 of `{created_at}|{id}`,
+/// matching the `(created_at, id)` keyset `db::get_user_files` orders and
+/// filters on.
+fn encode_files_cursor(created_at: chrono::NaiveDateTime, id: Uuid) -> String {
+    let raw = format!("{}|{}", created_at.and_utc().timestamp_micros(), id);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(raw)
+}
+
+fn decode_files_cursor(cursor: &str) -> Result<(chrono::NaiveDateTime, Uuid), ()> {
+    let raw = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(cursor)
+        .map_err(|_| ())?;
+    let raw = String::from_utf8(raw).map_err(|_| ())?;
+    let (micros, id) = raw.split_once('|').ok_or(())?;
+    let micros: i64 = micros.parse().map_err(|_| ())?;
+    let created_at = chrono::DateTime::from_timestamp_micros(micros)
+        .ok_or(())?
+        .naive_utc();
+    let id = Uuid::parse_str(id).map_err(|_| ())?;
+    Ok((created_at, id))
+}
+
+/// Turns the `{size: object_key}` map stored on a `File` row into
+/// `{size: download_url}`, pointing at the `preview_file` route below.
+pub(crate) fn preview_urls(
+    file_id: Uuid,
+    previews: &Option<serde_json::Value>,
+) -> HashMap<String, String> {
+    let Some(serde_json::Value::Object(sizes)) = previews else {
+        return HashMap::new();
+    };
+
+    sizes
+        .keys()
+        .map(|label| {
+            (
+                label.clone(),
+                format!("/api/files/{}/preview/{}", file_id, label),
+            )
+        })
+        .collect()
+}
+
 #[utoipa::path(
     delete,
     path = "/api/files/{file_id}",
@@ -192,53 +381,67 @@ pub async fn delete_file(
     qdrant: web::Data<QdrantService>,
     claims: web::ReqData<Claims>,
     file_id: web::Path<Uuid>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    let file = match db::get_file_by_id(&pool, &file_id, &user_id).await {
-        Ok(file) => file,
-        Err(e) => {
-            log::error!("Database error: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
-            })));
-        }
-    };
-
-    let file = match file {
-        Some(f) => f,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let file = db::get_file_by_id(&pool, &file_id, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
+
+    match blob_hash_from_path(&file.minio_path) {
+        Some(hash) => match db::decrement_blob_ref_count(&pool, hash).await {
+            Ok(remaining) if remaining <= 0 => {
+                minio.delete_file(&file.minio_path).await.ok();
+            }
+            Ok(_) => {}
+            Err(e) => log::error!("Database error decrementing blob refcount: {}", e),
+        },
         None => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "File not found"
-            })));
+            minio.delete_file(&file.minio_path).await.ok();
         }
-    };
-
-    minio.delete_file(&file.minio_path).await.ok();
+    }
 
     qdrant.delete_file_vectors(&user_id, &file_id).await.ok();
 
-    if let Err(e) = db::delete_file(&pool, &file_id, &user_id).await {
-        log::error!("Database error: {}", e);
-        return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-            "error": "Database error"
-        })));
-    }
+    db::delete_file(&pool, &file_id, &user_id).await?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "message": "File deleted successfully"
     })))
 }
 
+/// Parses a single-range `Range: bytes=start-end` header value. Multi-range
+/// requests aren't supported; only the first range is honored.
+fn parse_range_header(value: &str) -> Option<(u64, Option<u64>)> {
+    let spec = value.strip_prefix("bytes=")?;
+    let spec = spec.split(',').next()?.trim();
+    let (start, end) = spec.split_once('-')?;
+
+    if start.is_empty() {
+        // Suffix range ("bytes=-500" = last 500 bytes) isn't needed by the
+        // media-scrubbing use case this targets; it's ignored like a missing
+        // Range header, so the caller serves the full body with a 200.
+        return None;
+    }
+
+    let start: u64 = start.trim().parse().ok()?;
+    let end = if end.trim().is_empty() {
+        None
+    } else {
+        Some(end.trim().parse().ok()?)
+    };
+
+    Some((start, end))
+}
+
 #[utoipa::path(
     get,
     path = "/api/files/{file_id}/download",
     responses(
         (status = 200, description = "File content"),
+        (status = 206, description = "Partial file content for a Range request"),
         (status = 404, description = "File not found"),
+        (status = 416, description = "Requested range not satisfiable"),
         (status = 401, description = "Unauthorized")
     ),
     params(
@@ -253,48 +456,219 @@ pub async fn download_file(
     minio: web::Data<MinioClient>,
     claims: web::ReqData<Claims>,
     file_id: web::Path<Uuid>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    let file = match db::get_file_by_id(&pool, &file_id, &user_id).await {
-        Ok(Some(f)) => f,
-        Ok(None) => {
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "File not found"
-            })));
-        }
-        Err(e) => {
-            log::error!("Database error: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Database error"
-            })));
-        }
-    };
+    req: actix_web::HttpRequest,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
-    let file_data = match minio.download_file(&file.minio_path).await {
-        Ok(data) => data,
-        Err(e) => {
-            log::error!("MinIO error: {}", e);
-            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "Storage error"
-            })));
-        }
-    };
+    let file = db::get_file_by_id(&pool, &file_id, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
 
     let content_type = file
         .mime_type
+        .clone()
         .unwrap_or_else(|| "application/octet-stream".to_string());
+    let content_disposition = format!("inline; filename=\"{}\"", file.filename);
+
+    let range = req
+        .headers()
+        .get(actix_web::http::header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_range_header);
+
+    if file.enc_alg.is_some() {
+        // A ranged S3 GET can't compose with AEAD decryption (the
+        // authentication tag covers the whole ciphertext), so an encrypted
+        // object is always fetched and decrypted whole, then sliced in
+        // memory to satisfy a Range request.
+        let ciphertext = minio
+            .download_file(&file.minio_path)
+            .await
+            .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+        let plaintext = decrypt_file(&file, ciphertext)?;
+        let total_size = plaintext.len() as u64;
+
+        let Some((start, end)) = range else {
+            return Ok(HttpResponse::Ok()
+                .content_type(content_type)
+                .insert_header(("Accept-Ranges", "bytes"))
+                .insert_header(("Content-Disposition", content_disposition))
+                .body(plaintext));
+        };
+
+        let end = end.unwrap_or(total_size.saturating_sub(1));
+        if total_size == 0 || start > end || start >= total_size {
+            return Ok(HttpResponse::RangeNotSatisfiable()
+                .insert_header(("Content-Range", format!("bytes */{}", total_size)))
+                .finish());
+        }
+        let end = end.min(total_size - 1);
+
+        return Ok(HttpResponse::PartialContent()
+            .content_type(content_type)
+            .insert_header(("Accept-Ranges", "bytes"))
+            .insert_header(("Content-Range", format!("bytes {}-{}/{}", start, end, total_size)))
+            .insert_header(("Content-Disposition", content_disposition))
+            .body(plaintext[start as usize..=end as usize].to_vec()));
+    }
 
-    Ok(HttpResponse::Ok()
+    let Some((start, end)) = range else {
+        let file_data = minio
+            .download_file(&file.minio_path)
+            .await
+            .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+
+        return Ok(HttpResponse::Ok()
+            .content_type(content_type)
+            .insert_header(("Accept-Ranges", "bytes"))
+            .insert_header(("Content-Disposition", content_disposition))
+            .body(file_data));
+    };
+
+    let total_size = minio
+        .object_size(&file.minio_path)
+        .await
+        .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+
+    let end = end.unwrap_or(total_size.saturating_sub(1));
+    if total_size == 0 || start > end || start >= total_size {
+        return Ok(HttpResponse::RangeNotSatisfiable()
+            .insert_header(("Content-Range", format!("bytes */{}", total_size)))
+            .finish());
+    }
+    let end = end.min(total_size - 1);
+
+    let data = minio
+        .download_file_range(&file.minio_path, start, end)
+        .await
+        .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+
+    Ok(HttpResponse::PartialContent()
         .content_type(content_type)
-        .insert_header((
-            "Content-Disposition",
-            format!("inline; filename=\"{}\"", file.filename),
-        ))
-        .body(file_data))
+        .insert_header(("Accept-Ranges", "bytes"))
+        .insert_header(("Content-Range", format!("bytes {}-{}/{}", start, end, total_size)))
+        .insert_header(("Content-Disposition", content_disposition))
+        .body(data))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/files/{file_id}/download-url",
+    responses(
+        (status = 200, description = "Presigned MinIO URL the client can download from directly", body = DownloadUrlResponse),
+        (status = 400, description = "File is encrypted and must be downloaded through GET /files/{file_id}/download"),
+        (status = 404, description = "File not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    params(
+        ("file_id" = String, Path, description = "File ID")
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn download_url_file(
+    pool: web::Data<Pool<Postgres>>,
+    minio: web::Data<MinioClient>,
+    claims: web::ReqData<Claims>,
+    file_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let file = db::get_file_by_id(&pool, &file_id, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
+
+    if file.enc_alg.is_some() {
+        return Err(AppError::BadRequest(
+            "Encrypted files must be downloaded through GET /files/{file_id}/download".to_string(),
+        ));
+    }
+
+    let url = minio
+        .generate_presigned_get(&file.minio_path, DOWNLOAD_URL_TTL_SECONDS)
+        .await
+        .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(DownloadUrlResponse {
+        url,
+        expires_in: DOWNLOAD_URL_TTL_SECONDS as i64,
+    }))
+}
+
+/// Unwraps and decrypts an encrypted file's content. Returns an error if the
+/// row is missing any part of its envelope or authentication fails.
+fn decrypt_file(file: &crate::models::File, ciphertext: Vec<u8>) -> Result<Vec<u8>, AppError> {
+    let (Some(wrapped_key), Some(nonce), Some(alg)) = (
+        file.enc_key.as_deref(),
+        file.enc_nonce.as_deref(),
+        file.enc_alg.as_deref(),
+    ) else {
+        return Err(AppError::Internal(
+            "encrypted row missing envelope".to_string(),
+        ));
+    };
+
+    crypto::decrypt(&ciphertext, wrapped_key, nonce, alg)
+        .map_err(|e| AppError::Internal(format!("decryption failed: {}", e)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/files/{file_id}/preview/{size}",
+    responses(
+        (status = 200, description = "Preview image"),
+        (status = 404, description = "File or preview not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    params(
+        ("file_id" = String, Path, description = "File ID"),
+        ("size" = String, Path, description = "Preview size label, e.g. \"256\" or \"1024\"")
+    ),
+    security(
+        ("bearer" = [])
+    )
+)]
+pub async fn preview_file(
+    pool: web::Data<Pool<Postgres>>,
+    minio: web::Data<MinioClient>,
+    claims: web::ReqData<Claims>,
+    path: web::Path<(Uuid, String)>,
+) -> Result<HttpResponse, AppError> {
+    let (file_id, size) = path.into_inner();
+
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let file = db::get_file_by_id(&pool, &file_id, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("File not found".to_string()))?;
+
+    let preview_path = match &file.previews {
+        Some(serde_json::Value::Object(sizes)) => {
+            sizes.get(&size).and_then(|v| v.as_str()).map(String::from)
+        }
+        _ => None,
+    }
+    .ok_or_else(|| AppError::NotFound("Preview not found".to_string()))?;
+
+    let data = minio
+        .download_file(&preview_path)
+        .await
+        .map_err(|e| AppError::Storage(format!("MinIO error: {}", e)))?;
+
+    Ok(HttpResponse::Ok().content_type("image/jpeg").body(data))
+}
+
+/// Content-addressed key for a blob with this hex-encoded SHA-256 digest.
+fn blob_object_path(hash: &str) -> String {
+    format!("blobs/{}/{}", &hash[0..2], hash)
+}
+
+/// Recovers the blob hash from a `minio_path` produced by `blob_object_path`,
+/// so `delete_file` can find the right refcount row without storing the
+/// hash separately on the `File` row.
+fn blob_hash_from_path(minio_path: &str) -> Option<&str> {
+    minio_path.strip_prefix("blobs/")?.split('/').nth(1)
 }
 
 fn guess_mime_type(filename: &str) -> &'static str {
@@ -330,25 +704,108 @@ fn guess_mime_type(filename: &str) -> &'static str {
     }
 }
 
-fn chunk_text(text: &str, chunk_size: usize) -> Vec<String> {
-    let words: Vec<&str> = text.split_whitespace().collect();
-    let mut chunks = Vec::new();
-    let mut current_chunk = String::new();
+/// Default sliding-window size and overlap (characters) for `chunk_text`;
+/// overridable per deployment via `CHUNK_SIZE`/`CHUNK_OVERLAP` so an
+/// operator can trade off retrieval recall against chunk count.
+const DEFAULT_CHUNK_SIZE: usize = 500;
+const DEFAULT_CHUNK_OVERLAP: usize = 50;
+
+pub(crate) fn configured_chunk_size() -> usize {
+    env::var("CHUNK_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CHUNK_SIZE)
+}
+
+pub(crate) fn configured_chunk_overlap() -> usize {
+    env::var("CHUNK_OVERLAP")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(DEFAULT_CHUNK_OVERLAP)
+}
 
-    for word in words {
-        if current_chunk.len() + word.len() + 1 > chunk_size && !current_chunk.is_empty() {
-            chunks.push(current_chunk.clone());
-            current_chunk.clear();
+/// Splits `text` into overlapping windows of at most `chunk_size` characters,
+/// each one (after the first) seeded with the trailing `chunk_overlap`
+/// characters of the previous chunk so a sentence straddling a boundary
+/// still has its context on both sides. Within a window, prefers breaking on
+/// a paragraph (`\n\n`), then a sentence (`. `), then a word boundary, only
+/// hard-cutting mid-word when the window has none of those.
+pub(crate) fn chunk_text(text: &str, chunk_size: usize, chunk_overlap: usize) -> Vec<String> {
+    let text = text.trim();
+    if text.is_empty() || chunk_size == 0 {
+        return Vec::new();
+    }
+    let overlap = chunk_overlap.min(chunk_size - 1);
+
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < text.len() {
+        let window_end = floor_char_boundary(text, (start + chunk_size).min(text.len()));
+        let end = if window_end >= text.len() {
+            text.len()
+        } else {
+            chunk_break_point(text, start, window_end)
+        };
+
+        let chunk = text[start..end].trim();
+        if !chunk.is_empty() {
+            chunks.push(chunk.to_string());
         }
-        if !current_chunk.is_empty() {
-            current_chunk.push(' ');
+
+        if end >= text.len() {
+            break;
         }
-        current_chunk.push_str(word);
-    }
 
-    if !current_chunk.is_empty() {
-        chunks.push(current_chunk);
+        let overlap_start = floor_char_boundary(text, end.saturating_sub(overlap));
+        let next_start = snap_back_to_word_start(text, overlap_start);
+        // Always make forward progress even if overlap >= the distance just covered.
+        start = if next_start > start { next_start } else { end };
     }
 
     chunks
 }
+
+/// Finds the best place to end a chunk inside `text[start..window_end]`,
+/// falling back to the hard `window_end` cutoff when no paragraph, sentence,
+/// or word boundary is found in the window.
+fn chunk_break_point(text: &str, start: usize, window_end: usize) -> usize {
+    let window = &text[start..window_end];
+
+    if let Some(pos) = window.rfind("\n\n") {
+        return start + pos + "\n\n".len();
+    }
+    if let Some(pos) = window.rfind(". ") {
+        return start + pos + ". ".len();
+    }
+    if let Some(pos) = window.rfind(char::is_whitespace) {
+        let ws_len = window[pos..].chars().next().map_or(1, char::len_utf8);
+        return start + pos + ws_len;
+    }
+
+    window_end
+}
+
+/// Walks `idx` back to the start of the word it falls inside, so an overlap
+/// seed never begins mid-word. Falls back to `0` if no preceding whitespace
+/// exists.
+fn snap_back_to_word_start(text: &str, idx: usize) -> usize {
+    match text[..idx].rfind(char::is_whitespace) {
+        Some(pos) => {
+            let ws_len = text[pos..].chars().next().map_or(1, char::len_utf8);
+            pos + ws_len
+        }
+        None => 0,
+    }
+}
+
+/// The largest char boundary `<= index`; `str` slicing panics on a boundary
+/// that lands inside a multi-byte codepoint, so window edges computed from
+/// raw byte counts need to be snapped back before use.
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut idx = index.min(text.len());
+    while idx > 0 && !text.is_char_boundary(idx) {
+        idx -= 1;
+    }
+    idx
+}
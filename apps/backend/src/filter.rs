@@ -0,0 +1,418 @@
+//! Small boolean expression language for restricting semantic search to chunk
+//! metadata. Field names are matched against the Qdrant point payload, which
+//! `QdrantService::upsert_vectors` fills with `file_id` (string) and
+//! `chunk_index` (integer); other fields match no stored chunk.
+//!
+//! Grammar (highest to lowest precedence): parenthesized groups and
+//! comparisons, NOT, AND, OR. `>` and `<` accept numbers only.
+//!   expr       := or_expr
+//!   or_expr    := and_expr ("OR" and_expr)*
+//!   and_expr   := unary ("AND" unary)*
+//!   unary      := "NOT" unary | atom
+//!   atom       := "(" expr ")" | comparison
+//!   comparison := IDENT ("=" | "!=" | ">" | "<") value
+//!              | IDENT "IN" "[" value ("," value)* "]"
+//!   value      := STRING | NUMBER
+
+use qdrant_client::qdrant::{
+    r#match::MatchValue, Condition, FieldCondition, Filter, Match, Range,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Num(f64),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Eq(String, Value),
+    Ne(String, Value),
+    In(String, Vec<Value>),
+    Gt(String, Value),
+    Lt(String, Value),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterParseError {
+    pub message: String,
+}
+
+impl std::fmt::Display for FilterParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FilterParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Eq,
+    Ne,
+    Gt,
+    Lt,
+    And,
+    Or,
+    Not,
+    In,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, FilterParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            '[' => {
+                tokens.push(Token::LBracket);
+                i += 1;
+            }
+            ']' => {
+                tokens.push(Token::RBracket);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            '=' => {
+                tokens.push(Token::Eq);
+                i += 1;
+            }
+            '!' if chars.get(i + 1) == Some(&'=') => {
+                tokens.push(Token::Ne);
+                i += 2;
+            }
+            '>' => {
+                tokens.push(Token::Gt);
+                i += 1;
+            }
+            '<' => {
+                tokens.push(Token::Lt);
+                i += 1;
+            }
+            '"' => {
+                let mut s = String::new();
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    s.push(chars[i]);
+                    i += 1;
+                }
+                if i >= chars.len() {
+                    return Err(FilterParseError {
+                        message: "unterminated string literal".to_string(),
+                    });
+                }
+                i += 1; // closing quote
+                tokens.push(Token::Str(s));
+            }
+            c if c.is_ascii_digit() || (c == '-' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) => {
+                let start = i;
+                i += 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let s: String = chars[start..i].iter().collect();
+                let n = s.parse::<f64>().map_err(|_| FilterParseError {
+                    message: format!("invalid number literal `{}`", s),
+                })?;
+                tokens.push(Token::Num(n));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '.') {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                tokens.push(match word.to_uppercase().as_str() {
+                    "AND" => Token::And,
+                    "OR" => Token::Or,
+                    "NOT" => Token::Not,
+                    "IN" => Token::In,
+                    _ => Token::Ident(word),
+                });
+            }
+            other => {
+                return Err(FilterParseError {
+                    message: format!("unexpected character `{}`", other),
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let tok = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        tok
+    }
+
+    fn expect(&mut self, expected: &Token) -> Result<(), FilterParseError> {
+        match self.advance() {
+            Some(ref tok) if tok == expected => Ok(()),
+            other => Err(FilterParseError {
+                message: format!("expected {:?}, found {:?}", expected, other),
+            }),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, FilterParseError> {
+        let mut left = self.parse_and()?;
+        while matches!(self.peek(), Some(Token::Or)) {
+            self.advance();
+            let right = self.parse_and()?;
+            left = Expr::Or(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, FilterParseError> {
+        let mut left = self.parse_unary()?;
+        while matches!(self.peek(), Some(Token::And)) {
+            self.advance();
+            let right = self.parse_unary()?;
+            left = Expr::And(Box::new(left), Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, FilterParseError> {
+        if matches!(self.peek(), Some(Token::Not)) {
+            self.advance();
+            let inner = self.parse_unary()?;
+            return Ok(Expr::Not(Box::new(inner)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<Expr, FilterParseError> {
+        if matches!(self.peek(), Some(Token::LParen)) {
+            self.advance();
+            let expr = self.parse_or()?;
+            self.expect(&Token::RParen)?;
+            return Ok(expr);
+        }
+        self.parse_comparison()
+    }
+
+    fn parse_value(&mut self) -> Result<Value, FilterParseError> {
+        match self.advance() {
+            Some(Token::Str(s)) => Ok(Value::Str(s)),
+            Some(Token::Num(n)) => Ok(Value::Num(n)),
+            other => Err(FilterParseError {
+                message: format!("expected a value, found {:?}", other),
+            }),
+        }
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expr, FilterParseError> {
+        let field = match self.advance() {
+            Some(Token::Ident(name)) => name,
+            other => {
+                return Err(FilterParseError {
+                    message: format!("expected a field name, found {:?}", other),
+                })
+            }
+        };
+
+        match self.advance() {
+            Some(Token::Eq) => Ok(Expr::Eq(field, self.parse_value()?)),
+            Some(Token::Ne) => Ok(Expr::Ne(field, self.parse_value()?)),
+            Some(Token::Gt) => Ok(Expr::Gt(field, self.parse_value()?)),
+            Some(Token::Lt) => Ok(Expr::Lt(field, self.parse_value()?)),
+            Some(Token::In) => {
+                self.expect(&Token::LBracket)?;
+                let mut values = vec![self.parse_value()?];
+                while matches!(self.peek(), Some(Token::Comma)) {
+                    self.advance();
+                    values.push(self.parse_value()?);
+                }
+                self.expect(&Token::RBracket)?;
+                Ok(Expr::In(field, values))
+            }
+            other => Err(FilterParseError {
+                message: format!("expected a comparison operator, found {:?}", other),
+            }),
+        }
+    }
+}
+
+pub fn parse_filter(input: &str) -> Result<Expr, FilterParseError> {
+    let tokens = tokenize(input)?;
+    if tokens.is_empty() {
+        return Err(FilterParseError {
+            message: "empty filter expression".to_string(),
+        });
+    }
+
+    let mut parser = Parser { tokens, pos: 0 };
+    let expr = parser.parse_or()?;
+
+    if parser.pos != parser.tokens.len() {
+        return Err(FilterParseError {
+            message: format!("unexpected trailing tokens at position {}", parser.pos),
+        });
+    }
+
+    Ok(expr)
+}
+
+fn match_value(value: &Value) -> MatchValue {
+    match value {
+        Value::Str(s) => MatchValue::Keyword(s.clone()),
+        Value::Num(n) => MatchValue::Integer(*n as i64),
+    }
+}
+
+fn eq_condition(field: &str, value: &Value) -> Condition {
+    Condition::matches(field, match_value(value))
+}
+
+fn range_condition(
+    field: &str,
+    value: &Value,
+    greater_than: bool,
+) -> Result<Condition, FilterParseError> {
+    let bound = match value {
+        Value::Num(n) => *n,
+        Value::Str(s) => {
+            return Err(FilterParseError {
+                message: format!(
+                    "field \"{}\": {} only accepts a number, found string \"{}\"",
+                    field,
+                    if greater_than { ">" } else { "<" },
+                    s
+                ),
+            });
+        }
+    };
+
+    let range = if greater_than {
+        Range {
+            gt: Some(bound),
+            ..Default::default()
+        }
+    } else {
+        Range {
+            lt: Some(bound),
+            ..Default::default()
+        }
+    };
+
+    Ok(Condition {
+        condition_one_of: Some(qdrant_client::qdrant::condition::ConditionOneOf::Field(
+            FieldCondition {
+                key: field.to_string(),
+                range: Some(range),
+                ..Default::default()
+            },
+        )),
+    })
+}
+
+/// Compiles a parsed expression tree into a Qdrant `Filter`. AND becomes
+/// `must`; OR and NOT wrap each operand as a nested filter condition under
+/// `should`/`must_not`, so precedence is preserved without rewriting the tree.
+pub fn compile(expr: &Expr) -> Result<Filter, FilterParseError> {
+    Ok(match expr {
+        Expr::Eq(field, value) => Filter::must([eq_condition(field, value)]),
+        Expr::Ne(field, value) => Filter {
+            must_not: vec![eq_condition(field, value)],
+            ..Default::default()
+        },
+        Expr::In(field, values) => {
+            let should: Vec<Condition> = values.iter().map(|v| eq_condition(field, v)).collect();
+            Filter {
+                should,
+                ..Default::default()
+            }
+        }
+        Expr::Gt(field, value) => Filter::must([range_condition(field, value, true)?]),
+        Expr::Lt(field, value) => Filter::must([range_condition(field, value, false)?]),
+        Expr::And(left, right) => {
+            let mut must = Vec::new();
+            must.extend(compile(left)?.into_condition_vec());
+            must.extend(compile(right)?.into_condition_vec());
+            Filter {
+                must,
+                ..Default::default()
+            }
+        }
+        Expr::Or(left, right) => Filter {
+            should: vec![
+                nested_condition(compile(left)?),
+                nested_condition(compile(right)?),
+            ],
+            ..Default::default()
+        },
+        Expr::Not(inner) => Filter {
+            must_not: vec![nested_condition(compile(inner)?)],
+            ..Default::default()
+        },
+    })
+}
+
+fn nested_condition(filter: Filter) -> Condition {
+    Condition {
+        condition_one_of: Some(qdrant_client::qdrant::condition::ConditionOneOf::Filter(
+            filter,
+        )),
+    }
+}
+
+trait IntoConditionVec {
+    fn into_condition_vec(self) -> Vec<Condition>;
+}
+
+impl IntoConditionVec for Filter {
+    /// A filter produced purely by `must` conditions (the common case for a
+    /// single comparison) flattens directly; anything richer is nested so
+    /// precedence is preserved.
+    fn into_condition_vec(self) -> Vec<Condition> {
+        if self.should.is_empty() && self.must_not.is_empty() {
+            self.must
+        } else {
+            vec![nested_condition(self)]
+        }
+    }
+}
+
+pub fn parse_and_compile(input: &str) -> Result<Filter, FilterParseError> {
+    let expr = parse_filter(input)?;
+    compile(&expr)
+}
@@ -0,0 +1,221 @@
+//! Background ingestion pipeline: `upload_file` enqueues a job as soon as the
+//! bytes are safely in MinIO, and this worker extracts text, chunks/embeds/
+//! upserts it into Qdrant, and flips the file's `status` to `ready`,
+//! `not_indexed` (nothing extractable), or `failed` when done.
+
+use crate::db;
+use crate::embedding::Embedder;
+use crate::files::chunk_text;
+use crate::minio_service::MinioClient;
+use crate::models::File;
+use crate::qdrant_service::QdrantService;
+use serde::{Deserialize, Serialize};
+use sqlx::{Pool, Postgres};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IngestionJob {
+    pub user_id: Uuid,
+    pub file_id: Uuid,
+}
+
+impl IngestionJob {
+    pub fn to_payload(&self) -> String {
+        serde_json::to_string(self).expect("IngestionJob is always serializable")
+    }
+
+    pub fn from_payload(payload: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(payload)
+    }
+}
+
+pub async fn enqueue(
+    redis_client: &redis::Client,
+    job: &IngestionJob,
+) -> Result<(), redis::RedisError> {
+    crate::redis_service::enqueue_job(
+        redis_client,
+        crate::redis_service::INGESTION_QUEUE_KEY,
+        &job.to_payload(),
+    )
+    .await
+}
+
+/// Runs forever, popping ingestion jobs and processing them one at a time.
+/// Spawned once in `main` as a background task alongside the HTTP server.
+pub async fn run_worker(
+    pool: Pool<Postgres>,
+    redis_client: redis::Client,
+    minio: MinioClient,
+    qdrant: QdrantService,
+    embedder: Arc<dyn Embedder>,
+) {
+    match crate::redis_service::recover_processing_queue(
+        &redis_client,
+        crate::redis_service::INGESTION_QUEUE_KEY,
+    )
+    .await
+    {
+        Ok(0) => {}
+        Ok(recovered) => log::info!(
+            "Recovered {} ingestion job(s) left over from a previous run",
+            recovered
+        ),
+        Err(e) => log::error!("Failed to recover ingestion processing queue: {}", e),
+    }
+
+    loop {
+        let payload = match crate::redis_service::dequeue_job_reliable(
+            &redis_client,
+            crate::redis_service::INGESTION_QUEUE_KEY,
+            5.0,
+        )
+        .await
+        {
+            Ok(Some(payload)) => payload,
+            Ok(None) => continue,
+            Err(e) => {
+                log::error!("Ingestion queue error: {}", e);
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+
+        let job = match IngestionJob::from_payload(&payload) {
+            Ok(job) => job,
+            Err(e) => {
+                log::error!("Malformed ingestion job, dropping: {}", e);
+                ack(&redis_client, &payload).await;
+                continue;
+            }
+        };
+
+        if let Err(e) = process_job(&pool, &minio, &qdrant, embedder.as_ref(), &job).await {
+            log::error!("Ingestion job {} failed: {}", job.file_id, e);
+            db::update_file_status(&pool, &job.file_id, "failed")
+                .await
+                .ok();
+        }
+
+        ack(&redis_client, &payload).await;
+    }
+}
+
+/// Removes a job from the processing list once it's been handled, win or
+/// lose; a failure here just means `recover_processing_queue` re-delivers
+/// it next restart, which is harmless since processing is idempotent.
+async fn ack(redis_client: &redis::Client, payload: &str) {
+    if let Err(e) = crate::redis_service::ack_job(
+        redis_client,
+        crate::redis_service::INGESTION_QUEUE_KEY,
+        payload,
+    )
+    .await
+    {
+        log::error!("Failed to ack ingestion job: {}", e);
+    }
+}
+
+async fn process_job(
+    pool: &Pool<Postgres>,
+    minio: &MinioClient,
+    qdrant: &QdrantService,
+    embedder: &dyn Embedder,
+    job: &IngestionJob,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let file = db::get_file_by_id(pool, &job.file_id, &job.user_id)
+        .await?
+        .ok_or("file not found")?;
+
+    let raw_data = minio.download_file(&file.minio_path).await?;
+    let file_data = match (&file.enc_key, &file.enc_nonce, &file.enc_alg) {
+        (Some(wrapped_key), Some(nonce), Some(alg)) => {
+            crate::crypto::decrypt(&raw_data, wrapped_key, nonce, alg)?
+        }
+        _ => raw_data,
+    };
+
+    if file
+        .mime_type
+        .as_deref()
+        .is_some_and(crate::thumbnails::is_previewable)
+    {
+        if let Err(e) = generate_and_store_previews(pool, minio, &file, &file_data).await {
+            log::warn!("Preview generation failed for file {}: {}", file.id, e);
+        }
+    }
+
+    let text_content = file
+        .mime_type
+        .as_deref()
+        .and_then(|mime_type| crate::extract::extract_text(mime_type, &file_data));
+
+    let status = match text_content {
+        Some(text_content) => {
+            let chunks = chunk_text(
+                &text_content,
+                crate::files::configured_chunk_size(),
+                crate::files::configured_chunk_overlap(),
+            );
+
+            qdrant
+                .ensure_collection_exists(&job.user_id, embedder.dimension() as u64)
+                .await?;
+
+            // `upsert_vectors` assigns fresh point ids, so drop whatever an
+            // earlier run of this job stored before re-inserting.
+            qdrant
+                .delete_file_vectors(&job.user_id, &job.file_id)
+                .await?;
+
+            let vectors = embedder.embed_batch(&chunks).await?;
+            let embeddings: Vec<(usize, String, Vec<f32>)> = chunks
+                .into_iter()
+                .zip(vectors)
+                .enumerate()
+                .map(|(idx, (chunk, vector))| (idx, chunk, vector))
+                .collect();
+
+            qdrant
+                .upsert_vectors(&job.user_id, &job.file_id, embeddings)
+                .await?;
+
+            "ready"
+        }
+        // Nothing extractable (unsupported format, empty document, etc.) —
+        // the file itself is still stored and downloadable, it just has no
+        // vectors to search over.
+        None => "not_indexed",
+    };
+
+    db::update_file_status(pool, &job.file_id, status).await?;
+
+    Ok(())
+}
+
+/// Renders the configured preview sizes for an image upload, stores each one
+/// in MinIO next to the original, and records the size->object-key map on
+/// the file row.
+async fn generate_and_store_previews(
+    pool: &Pool<Postgres>,
+    minio: &MinioClient,
+    file: &File,
+    file_data: &[u8],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let previews = crate::thumbnails::generate_previews(file_data)?;
+    if previews.is_empty() {
+        return Ok(());
+    }
+
+    let mut preview_paths = HashMap::with_capacity(previews.len());
+    for (label, jpeg_bytes) in previews {
+        let preview_path = format!("{}.preview-{}.jpg", file.minio_path, label);
+        minio.put_object(&preview_path, &jpeg_bytes).await?;
+        preview_paths.insert(label, preview_path);
+    }
+
+    db::set_file_previews(pool, &file.id, &serde_json::to_value(preview_paths)?).await?;
+    Ok(())
+}
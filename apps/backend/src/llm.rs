@@ -0,0 +1,209 @@
+//! Pluggable answer-generation backends for `/api/rag/query`. Handlers only
+//! depend on the `LlmClient` trait, so swapping providers is a matter of
+//! constructing a different implementation in `main` — mirrors how
+//! `embedding::Embedder` decouples `QdrantService` from a specific provider.
+
+use async_trait::async_trait;
+use std::env;
+use std::time::Duration;
+
+#[async_trait]
+pub trait LlmClient: Send + Sync {
+    /// Answers `prompt` given `context`, the numbered chunks retrieved for it.
+    async fn complete(
+        &self,
+        prompt: &str,
+        context: &[String],
+    ) -> Result<String, Box<dyn std::error::Error>>;
+}
+
+/// Picks an `OpenAiLlm` when `LLM_API_KEY` is set, otherwise the `MockLlm`
+/// stub — so a deployment without an LLM key still gets a usable (if
+/// unintelligent) `/api/rag/query` response instead of a hard failure.
+pub fn create_llm_client() -> Box<dyn LlmClient> {
+    if env::var("LLM_API_KEY").is_ok() {
+        Box::new(OpenAiLlm::from_env())
+    } else {
+        log::warn!("LLM_API_KEY not set; falling back to MockLlm for /api/rag/query");
+        Box::new(MockLlm)
+    }
+}
+
+const SYSTEM_PROMPT: &str = "You are a helpful assistant answering questions about the user's \
+uploaded documents. Use only the numbered context chunks below to answer; if they don't contain \
+the answer, say so instead of guessing.";
+
+/// HTTP client for any OpenAI-compatible `/v1/chat/completions` endpoint
+/// (OpenAI itself, or a self-hosted gateway like LocalAI/vLLM).
+pub struct OpenAiLlm {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    max_retries: u32,
+}
+
+#[derive(Clone, serde::Serialize)]
+struct ChatMessage {
+    role: &'static str,
+    content: String,
+}
+
+#[derive(serde::Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [ChatMessage],
+}
+
+#[derive(serde::Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatChoice {
+    message: ChatResponseMessage,
+}
+
+#[derive(serde::Deserialize)]
+struct ChatResponseMessage {
+    content: String,
+}
+
+impl OpenAiLlm {
+    pub fn from_env() -> Self {
+        let base_url =
+            env::var("LLM_BASE_URL").unwrap_or_else(|_| "https://api.openai.com/v1".to_string());
+        let api_key = env::var("LLM_API_KEY").unwrap_or_default();
+        let model = env::var("LLM_MODEL").unwrap_or_else(|_| "gpt-4o-mini".to_string());
+
+        OpenAiLlm {
+            client: reqwest::Client::new(),
+            base_url,
+            api_key,
+            model,
+            max_retries: 3,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for OpenAiLlm {
+    async fn complete(
+        &self,
+        prompt: &str,
+        context: &[String],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let messages = [
+            ChatMessage {
+                role: "system",
+                content: SYSTEM_PROMPT.to_string(),
+            },
+            ChatMessage {
+                role: "user",
+                content: build_user_message(prompt, context),
+            },
+        ];
+
+        let mut attempt = 0;
+        loop {
+            let response = self
+                .client
+                .post(&url)
+                .bearer_auth(&self.api_key)
+                .json(&ChatRequest {
+                    model: &self.model,
+                    messages: &messages,
+                })
+                .send()
+                .await;
+
+            match response {
+                Ok(resp) if resp.status().is_success() => {
+                    let parsed: ChatResponse = resp.json().await?;
+                    return Ok(parsed
+                        .choices
+                        .into_iter()
+                        .next()
+                        .map(|choice| choice.message.content)
+                        .unwrap_or_default());
+                }
+                Ok(resp) if resp.status().is_server_error() && attempt < self.max_retries => {
+                    attempt += 1;
+                    let backoff = Duration::from_millis(200 * 2u64.pow(attempt));
+                    tokio::time::sleep(backoff).await;
+                }
+                Ok(resp) => {
+                    let status = resp.status();
+                    let body = resp.text().await.unwrap_or_default();
+                    return Err(format!("llm request failed ({}): {}", status, body).into());
+                }
+                Err(e) if attempt < self.max_retries => {
+                    attempt += 1;
+                    let backoff = Duration::from_millis(200 * 2u64.pow(attempt));
+                    tokio::time::sleep(backoff).await;
+                    log::warn!("llm request error (retry {}): {}", attempt, e);
+                }
+                Err(e) => return Err(Box::new(e)),
+            }
+        }
+    }
+}
+
+/// Numbers each context chunk so the model (and the system prompt) can
+/// refer back to "[2]" etc.; falls back to the bare query when nothing was
+/// retrieved.
+fn build_user_message(query: &str, context: &[String]) -> String {
+    if context.is_empty() {
+        return format!("Question: {}", query);
+    }
+
+    let numbered_context = context
+        .iter()
+        .enumerate()
+        .map(|(i, chunk)| format!("[{}] {}", i + 1, chunk))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    format!("Context:\n{}\n\nQuestion: {}", numbered_context, query)
+}
+
+/// Deterministic stand-in used when no LLM is configured: echoes the
+/// retrieved context the way the original `rag::generate_answer` stub did.
+pub struct MockLlm;
+
+#[async_trait]
+impl LlmClient for MockLlm {
+    async fn complete(
+        &self,
+        prompt: &str,
+        context: &[String],
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        if context.is_empty() {
+            return Ok(format!(
+                "I don't have enough information in your uploaded documents to answer: \"{}\".\n\n\
+                 Please upload relevant documents first.",
+                prompt
+            ));
+        }
+
+        let joined = context.join("\n\n");
+        Ok(format!(
+            "Based on your documents, here's what I found regarding \"{}\":\n\n{}",
+            prompt,
+            truncate_at_char_boundary(&joined, 500)
+        ))
+    }
+}
+
+/// Truncates `s` to at most `max_bytes` bytes, backing off to the nearest
+/// preceding char boundary so a multibyte UTF-8 character straddling the cut
+/// point doesn't panic the slice.
+fn truncate_at_char_boundary(s: &str, max_bytes: usize) -> &str {
+    let mut end = s.len().min(max_bytes);
+    while end > 0 && !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
@@ -0,0 +1,38 @@
+//! Minimal SMTP mail sending, used today only for magic-link sign-in
+//! emails. Configured entirely via `SMTP_*` env vars; if `SMTP_HOST` isn't
+//! set, logs the message instead of sending it so local dev doesn't need a
+//! mail server.
+
+use lettre::message::Message;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+use std::env;
+
+pub fn send_email(to: &str, subject: &str, body: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let Ok(smtp_host) = env::var("SMTP_HOST") else {
+        log::info!(
+            "SMTP_HOST not set; logging email instead of sending it:\nTo: {}\nSubject: {}\n{}",
+            to,
+            subject,
+            body
+        );
+        return Ok(());
+    };
+
+    let from = env::var("SMTP_FROM").unwrap_or_else(|_| "no-reply@localhost".to_string());
+    let username = env::var("SMTP_USERNAME").unwrap_or_default();
+    let password = env::var("SMTP_PASSWORD").unwrap_or_default();
+
+    let email = Message::builder()
+        .from(from.parse()?)
+        .to(to.parse()?)
+        .subject(subject)
+        .body(body.to_string())?;
+
+    let mailer = SmtpTransport::relay(&smtp_host)?
+        .credentials(Credentials::new(username, password))
+        .build();
+
+    mailer.send(&email)?;
+    Ok(())
+}
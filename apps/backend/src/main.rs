@@ -1,20 +1,37 @@
+mod admin;
 mod auth;
+mod crypto;
 mod db;
+mod embedding;
+mod errors;
+mod extract;
 mod files;
+mod filter;
+mod ingestion;
+mod llm;
+mod mailer;
+mod markdown;
 mod memos;
 mod minio_service;
 mod models;
+mod oauth;
+mod permissions;
 mod qdrant_service;
 mod rag;
 mod redis_service;
+mod sharing;
+mod thumbnails;
+mod validate;
 
 use actix_cors::Cors;
 use actix_web::{middleware, web, App, HttpResponse, HttpServer, Responder};
 use actix_web_httpauth::middleware::HttpAuthentication;
+use embedding::{Embedder, OpenAiEmbedder};
 use minio_service::MinioClient;
 use qdrant_service::QdrantService;
 use sqlx::postgres::PgPoolOptions;
 use std::env;
+use std::sync::Arc;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
@@ -22,17 +39,28 @@ use utoipa_swagger_ui::SwaggerUi;
 #[openapi(
     paths(
         health,
+        // Admin endpoints
+        admin::block_user,
+        admin::unblock_user,
         // Auth endpoints
         auth::register,
         auth::login,
         auth::refresh,
         auth::logout,
         auth::me,
+        oauth::oauth_start,
+        oauth::oauth_callback,
+        auth::request_magic_link,
+        auth::verify_magic_link,
+        auth::list_sessions,
+        auth::revoke_session,
         // File endpoints
         files::upload_file,
         files::list_files,
         files::delete_file,
         files::download_file,
+        files::download_url_file,
+        files::preview_file,
         // RAG endpoints
         rag::search,
         rag::rag_query,
@@ -45,6 +73,11 @@ use utoipa_swagger_ui::SwaggerUi;
         memos::get_memo_messages,
         memos::create_memo_message,
         memos::attach_file_to_message,
+        memos::share_memo,
+        memos::revoke_memo_share,
+        memos::publish_memo,
+        memos::unpublish_memo,
+        memos::get_shared_memo,
     ),
     components(
         schemas(
@@ -54,8 +87,13 @@ use utoipa_swagger_ui::SwaggerUi;
             models::RefreshRequest,
             models::AuthResponse,
             models::UserResponse,
+            models::MagicLinkRequest,
+            models::SessionResponse,
+            models::ListSessionsResponse,
             // File models
             models::FileResponse,
+            models::FileListResponse,
+            models::DownloadUrlResponse,
             // RAG models
             models::SearchRequest,
             models::SearchResponse,
@@ -69,11 +107,17 @@ use utoipa_swagger_ui::SwaggerUi;
             models::CreateMemoMessageRequest,
             models::MemoMessageResponse,
             models::MemoAttachmentResponse,
+            models::ShareMemoRequest,
+            models::MemoPermissionResponse,
+            models::PublishMemoRequest,
+            models::MemoShareResponse,
+            models::SharedMemoResponse,
             // Error models
             models::ErrorResponse,
         )
     ),
     tags(
+        (name = "admin", description = "Account moderation endpoints, gated on an admin role claim"),
         (name = "auth", description = "Authentication and authorization endpoints with JWT refresh token support"),
         (name = "files", description = "File upload and management endpoints"),
         (name = "rag", description = "RAG query and vector search endpoints"),
@@ -140,6 +184,17 @@ async fn main() -> std::io::Result<()> {
         .expect("Failed to initialize Qdrant client");
     log::info!("Qdrant client initialized");
 
+    let embedder: Arc<dyn Embedder> = Arc::new(OpenAiEmbedder::from_env());
+    let llm: Arc<dyn llm::LlmClient> = llm::create_llm_client().into();
+
+    tokio::spawn(ingestion::run_worker(
+        pool.clone(),
+        redis_client.clone(),
+        minio.clone(),
+        qdrant.clone(),
+        embedder.clone(),
+    ));
+
     let host = env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
     let port = env::var("PORT").unwrap_or_else(|_| "8080".to_string());
     let bind_address = format!("{}:{}", host, port);
@@ -160,7 +215,10 @@ async fn main() -> std::io::Result<()> {
             .app_data(web::Data::new(redis_client.clone()))
             .app_data(web::Data::new(minio.clone()))
             .app_data(web::Data::new(qdrant.clone()))
+            .app_data(web::Data::new(embedder.clone()))
+            .app_data(web::Data::new(llm.clone()))
             .wrap(middleware::Logger::default())
+            .wrap(middleware::Compress::default())
             .wrap(cors)
             .service(
                 web::scope("/api")
@@ -171,11 +229,45 @@ async fn main() -> std::io::Result<()> {
                             .route("/register", web::post().to(auth::register))
                             .route("/login", web::post().to(auth::login))
                             .route("/refresh", web::post().to(auth::refresh))
+                            .route(
+                                "/oauth/{provider}/start",
+                                web::get().to(oauth::oauth_start),
+                            )
+                            .route(
+                                "/oauth/{provider}/callback",
+                                web::get().to(oauth::oauth_callback),
+                            )
+                            .route(
+                                "/magic-link",
+                                web::post().to(auth::request_magic_link),
+                            )
+                            .route(
+                                "/magic-link/verify",
+                                web::get().to(auth::verify_magic_link),
+                            )
                             .service(
                                 web::scope("")
                                     .wrap(bearer_middleware.clone())
                                     .route("/logout", web::post().to(auth::logout))
-                                    .route("/me", web::get().to(auth::me)),
+                                    .route("/me", web::get().to(auth::me))
+                                    .route("/sessions", web::get().to(auth::list_sessions))
+                                    .route(
+                                        "/sessions/{family}",
+                                        web::delete().to(auth::revoke_session),
+                                    ),
+                            ),
+                    )
+                    // Admin routes
+                    .service(
+                        web::scope("/admin")
+                            .wrap(bearer_middleware.clone())
+                            .route(
+                                "/users/{id}/block",
+                                web::post().to(admin::block_user),
+                            )
+                            .route(
+                                "/users/{id}/unblock",
+                                web::post().to(admin::unblock_user),
                             ),
                     )
                     // File routes
@@ -185,7 +277,15 @@ async fn main() -> std::io::Result<()> {
                             .route("/upload", web::post().to(files::upload_file))
                             .route("", web::get().to(files::list_files))
                             .route("/{file_id}", web::delete().to(files::delete_file))
-                            .route("/{file_id}/download", web::get().to(files::download_file)),
+                            .route("/{file_id}/download", web::get().to(files::download_file))
+                            .route(
+                                "/{file_id}/download-url",
+                                web::get().to(files::download_url_file),
+                            )
+                            .route(
+                                "/{file_id}/preview/{size}",
+                                web::get().to(files::preview_file),
+                            ),
                     )
                     // Memos routes
                     .service(
@@ -206,8 +306,23 @@ async fn main() -> std::io::Result<()> {
                             .route(
                                 "/{memo_id}/messages/{message_id}/attach/{file_id}",
                                 web::post().to(memos::attach_file_to_message),
+                            )
+                            .route("/{memo_id}/share", web::post().to(memos::share_memo))
+                            .route(
+                                "/{memo_id}/share/{user_id}",
+                                web::delete().to(memos::revoke_memo_share),
+                            )
+                            .route("/{memo_id}/publish", web::post().to(memos::publish_memo))
+                            .route(
+                                "/{memo_id}/publish",
+                                web::delete().to(memos::unpublish_memo),
                             ),
                     )
+                    // Unauthenticated public read-only memo share links
+                    .service(
+                        web::scope("/shared")
+                            .route("/{slug}", web::get().to(memos::get_shared_memo)),
+                    )
                     // RAG routes (legacy, kept for compatibility)
                     .service(
                         web::scope("/rag")
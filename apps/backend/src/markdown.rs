@@ -0,0 +1,93 @@
+//! Server-side Markdown rendering for memo message content. Rendered once at
+//! write time and cached in `memo_messages.content_html` so `GET
+//! /memos/{id}/messages` never re-parses Markdown or re-runs syntax
+//! highlighting on read. Fenced code blocks are highlighted by their declared
+//! language, and the resulting HTML is always run through an allowlist
+//! sanitizer before being cached, since message content is arbitrary user
+//! input.
+
+use pulldown_cmark::{CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use std::sync::OnceLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::html::{styled_line_to_highlighted_html, IncludeBackground};
+use syntect::parsing::SyntaxSet;
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SET: OnceLock<SyntaxSet> = OnceLock::new();
+    SET.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+fn theme() -> &'static Theme {
+    static THEME: OnceLock<Theme> = OnceLock::new();
+    THEME.get_or_init(|| ThemeSet::load_defaults().themes["InspiredGitHub"].clone())
+}
+
+/// Renders raw Markdown `content` to sanitized HTML. Always safe to embed
+/// directly in a page: raw HTML in `content` goes through the same allowlist
+/// sanitizer, so tags and attributes outside the allowlist are removed.
+pub fn render(content: &str) -> String {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+
+    let mut events = Vec::new();
+    let mut code_block_lang: Option<String> = None;
+    let mut code_block_buf = String::new();
+
+    for event in Parser::new_ext(content, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang))) => {
+                code_block_lang = Some(lang.to_string());
+                code_block_buf.clear();
+            }
+            Event::Text(text) if code_block_lang.is_some() => {
+                code_block_buf.push_str(&text);
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                let lang = code_block_lang.take().unwrap_or_default();
+                events.push(Event::Html(highlight(&lang, &code_block_buf).into()));
+            }
+            other => events.push(other),
+        }
+    }
+
+    let mut unsafe_html = String::new();
+    pulldown_cmark::html::push_html(&mut unsafe_html, events.into_iter());
+
+    sanitize(&unsafe_html)
+}
+
+/// Highlights a fenced code block's contents as an HTML `<pre><code>` block.
+/// Falls back to plain, unhighlighted text for an unrecognized `lang`.
+fn highlight(lang: &str, code: &str) -> String {
+    let syntax_set = syntax_set();
+    let syntax = syntax_set
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| syntax_set.find_syntax_plain_text());
+
+    let mut highlighter = HighlightLines::new(syntax, theme());
+    let mut html = String::from("<pre><code>");
+    for line in code.lines() {
+        let Ok(ranges) = highlighter.highlight_line(line, syntax_set) else {
+            continue;
+        };
+        if let Ok(line_html) = styled_line_to_highlighted_html(&ranges, IncludeBackground::No) {
+            html.push_str(&line_html);
+        }
+        html.push('\n');
+    }
+    html.push_str("</code></pre>");
+    html
+}
+
+/// Strips the rendered HTML down to an allowlist of tags/attributes so
+/// stored message content can never inject arbitrary markup or scripts.
+fn sanitize(unsafe_html: &str) -> String {
+    ammonia::Builder::default()
+        .add_tags(["span"])
+        .add_tag_attributes("span", ["style"])
+        .add_tag_attributes("code", ["class"])
+        .clean(unsafe_html)
+        .to_string()
+}
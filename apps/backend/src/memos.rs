@@ -1,9 +1,13 @@
 use crate::db;
+use crate::errors::AppError;
 use crate::models::{
-    Claims, CreateMemoMessageRequest, CreateMemoRequest, MemoAttachmentResponse,
-    MemoMessageResponse, MemoResponse,
+    Claims, CreateMemoMessageRequest, CreateMemoRequest, GetMemoMessagesQuery,
+    MemoAttachmentResponse, MemoMessageResponse, MemoPermissionResponse, MemoResponse,
+    MemoShareResponse, PublishMemoRequest, SharedMemoResponse, ShareMemoRequest,
 };
-use actix_web::{web, Error, HttpResponse};
+use crate::permissions::{self, PermissionType};
+use crate::sharing;
+use actix_web::{web, HttpResponse};
 use sqlx::{Pool, Postgres};
 use uuid::Uuid;
 
@@ -24,18 +28,10 @@ pub async fn create_memo(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
     req: web::Json<CreateMemoRequest>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
-    let memo = db::create_memo(&pool, &user_id, &req.title, req.description.as_deref())
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+    let memo = db::create_memo(&pool, &user_id, &req.title, req.description.as_deref()).await?;
 
     let message_count = 0;
 
@@ -64,32 +60,29 @@ pub async fn create_memo(
 pub async fn list_memos(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    let memos = db::get_user_memos(&pool, &user_id).await.map_err(|e| {
-        log::error!("Database error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
-
-    let mut responses = Vec::new();
-    for memo in memos {
-        let message_count = db::get_memo_message_count(&pool, &memo.id)
-            .await
-            .unwrap_or(0);
-
-        responses.push(MemoResponse {
-            id: memo.id,
-            title: memo.title,
-            description: memo.description,
-            message_count,
-            created_at: memo.created_at,
-            updated_at: memo.updated_at,
-        });
-    }
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let memos = db::get_user_memos(&pool, &user_id).await?;
+
+    let memo_ids: Vec<Uuid> = memos.iter().map(|memo| memo.id).collect();
+    let mut counts = db::get_memo_message_counts(&pool, &memo_ids).await?;
+
+    let responses: Vec<MemoResponse> = memos
+        .into_iter()
+        .map(|memo| {
+            let message_count = counts.remove(&memo.id).unwrap_or(0);
+
+            MemoResponse {
+                id: memo.id,
+                title: memo.title,
+                description: memo.description,
+                message_count,
+                created_at: memo.created_at,
+                updated_at: memo.updated_at,
+            }
+        })
+        .collect();
 
     Ok(HttpResponse::Ok().json(responses))
 }
@@ -114,36 +107,25 @@ pub async fn get_memo(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
     memo_id: web::Path<Uuid>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
     let memo = db::get_memo_by_id(&pool, &memo_id, &user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Memo not found".to_string()))?;
+
+    let message_count = db::get_memo_message_count(&pool, &memo.id)
         .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    match memo {
-        Some(m) => {
-            let message_count = db::get_memo_message_count(&pool, &m.id).await.unwrap_or(0);
-
-            Ok(HttpResponse::Ok().json(MemoResponse {
-                id: m.id,
-                title: m.title,
-                description: m.description,
-                message_count,
-                created_at: m.created_at,
-                updated_at: m.updated_at,
-            }))
-        }
-        None => Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Memo not found"
-        }))),
-    }
+        .unwrap_or(0);
+
+    Ok(HttpResponse::Ok().json(MemoResponse {
+        id: memo.id,
+        title: memo.title,
+        description: memo.description,
+        message_count,
+        created_at: memo.created_at,
+        updated_at: memo.updated_at,
+    }))
 }
 
 #[utoipa::path(
@@ -154,6 +136,7 @@ pub async fn get_memo(
     ),
     responses(
         (status = 200, description = "Memo deleted successfully"),
+        (status = 403, description = "Manage access required"),
         (status = 404, description = "Memo not found"),
         (status = 401, description = "Unauthorized")
     ),
@@ -166,27 +149,19 @@ pub async fn delete_memo(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
     memo_id: web::Path<Uuid>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
-    let deleted = db::delete_memo(&pool, &memo_id, &user_id)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+    require_memo_permission(&pool, &memo_id, &user_id, PermissionType::Manage).await?;
+
+    let deleted = db::delete_memo(&pool, &memo_id).await?;
 
     if deleted {
         Ok(HttpResponse::Ok().json(serde_json::json!({
             "message": "Memo deleted successfully"
         })))
     } else {
-        Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Memo not found"
-        })))
+        Err(AppError::NotFound("Memo not found".to_string()))
     }
 }
 
@@ -194,7 +169,8 @@ pub async fn delete_memo(
     get,
     path = "/api/memos/{memo_id}/messages",
     params(
-        ("memo_id" = Uuid, Path, description = "Memo ID")
+        ("memo_id" = Uuid, Path, description = "Memo ID"),
+        GetMemoMessagesQuery
     ),
     responses(
         (status = 200, description = "List of memo messages", body = Vec<MemoMessageResponse>),
@@ -210,44 +186,47 @@ pub async fn get_memo_messages(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
     memo_id: web::Path<Uuid>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
-
-    let messages = db::get_memo_messages(&pool, &memo_id, &user_id)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    let mut responses = Vec::new();
-    for message in messages {
-        let attachments_data = db::get_message_attachments(&pool, &message.id)
-            .await
-            .unwrap_or_default();
-
-        let attachments: Vec<MemoAttachmentResponse> = attachments_data
-            .into_iter()
-            .map(|(att, file)| MemoAttachmentResponse {
-                id: att.id,
-                filename: file.filename,
-                mime_type: file.mime_type,
-                file_size: file.file_size,
-                created_at: att.created_at,
-            })
-            .collect();
-
-        responses.push(MemoMessageResponse {
-            id: message.id,
-            content: message.content,
-            role: message.role,
-            attachments,
-            created_at: message.created_at,
-        });
-    }
+    query: web::Query<GetMemoMessagesQuery>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+    let render = query.render.unwrap_or(true);
+
+    let messages = db::get_memo_messages(&pool, &memo_id, &user_id).await?;
+
+    let message_ids: Vec<Uuid> = messages.iter().map(|message| message.id).collect();
+    let mut attachments_by_message = db::get_attachments_for_messages(&pool, &message_ids).await?;
+
+    let responses: Vec<MemoMessageResponse> = messages
+        .into_iter()
+        .map(|message| {
+            let attachments: Vec<MemoAttachmentResponse> = attachments_by_message
+                .remove(&message.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(att, file)| {
+                    let thumbnail_url = thumbnail_url(file.id, &file.previews);
+
+                    MemoAttachmentResponse {
+                        id: att.id,
+                        filename: file.filename,
+                        mime_type: file.mime_type,
+                        file_size: file.file_size,
+                        thumbnail_url,
+                        created_at: att.created_at,
+                    }
+                })
+                .collect();
+
+            MemoMessageResponse {
+                id: message.id,
+                content: message.content,
+                content_html: render.then_some(message.content_html),
+                role: message.role,
+                attachments,
+                created_at: message.created_at,
+            }
+        })
+        .collect();
 
     Ok(HttpResponse::Ok().json(responses))
 }
@@ -261,6 +240,7 @@ pub async fn get_memo_messages(
     request_body = CreateMemoMessageRequest,
     responses(
         (status = 200, description = "Message created successfully", body = MemoMessageResponse),
+        (status = 403, description = "Write access required"),
         (status = 404, description = "Memo not found"),
         (status = 401, description = "Unauthorized")
     ),
@@ -274,32 +254,12 @@ pub async fn create_memo_message(
     claims: web::ReqData<Claims>,
     memo_id: web::Path<Uuid>,
     req: web::Json<CreateMemoMessageRequest>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
-    // Verify memo exists and belongs to user
-    let memo = db::get_memo_by_id(&pool, &memo_id, &user_id)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    if memo.is_none() {
-        return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Memo not found"
-        })));
-    }
+    require_memo_permission(&pool, &memo_id, &user_id, PermissionType::Write).await?;
 
-    let message = db::create_memo_message(&pool, &memo_id, &user_id, &req.content, "user")
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+    let message = db::create_memo_message(&pool, &memo_id, &user_id, &req.content, "user").await?;
 
     // TODO: In the future, here we would:
     // 1. Process the message with AI
@@ -309,6 +269,7 @@ pub async fn create_memo_message(
     Ok(HttpResponse::Ok().json(MemoMessageResponse {
         id: message.id,
         content: message.content,
+        content_html: Some(message.content_html),
         role: message.role,
         attachments: vec![],
         created_at: message.created_at,
@@ -325,6 +286,7 @@ pub async fn create_memo_message(
     ),
     responses(
         (status = 200, description = "File attached successfully"),
+        (status = 403, description = "Write access required"),
         (status = 404, description = "Not found"),
         (status = 401, description = "Unauthorized")
     ),
@@ -337,50 +299,296 @@ pub async fn attach_file_to_message(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
     path: web::Path<(Uuid, Uuid, Uuid)>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
     let (memo_id, message_id, file_id) = path.into_inner();
 
-    // Verify memo belongs to user
-    let memo = db::get_memo_by_id(&pool, &memo_id, &user_id)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
-
-    if memo.is_none() {
-        return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "Memo not found"
-        })));
-    }
+    require_memo_permission(&pool, &memo_id, &user_id, PermissionType::Write).await?;
 
-    // Verify file belongs to user
-    let file = db::get_file_by_id(&pool, &file_id, &user_id)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+    let file = db::get_file_by_id(&pool, &file_id, &user_id).await?;
 
     if file.is_none() {
-        return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "error": "File not found"
-        })));
+        return Err(AppError::NotFound("File not found".to_string()));
     }
 
-    db::create_memo_attachment(&pool, &message_id, &file_id)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+    db::create_memo_attachment(&pool, &message_id, &file_id).await?;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "message": "File attached successfully"
     })))
 }
+
+#[utoipa::path(
+    post,
+    path = "/api/memos/{memo_id}/share",
+    params(
+        ("memo_id" = Uuid, Path, description = "Memo ID")
+    ),
+    request_body = ShareMemoRequest,
+    responses(
+        (status = 200, description = "Share granted or updated", body = MemoPermissionResponse),
+        (status = 400, description = "Unrecognized permission level"),
+        (status = 403, description = "Manage access required"),
+        (status = 404, description = "Memo not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    tag = "memos"
+)]
+pub async fn share_memo(
+    pool: web::Data<Pool<Postgres>>,
+    claims: web::ReqData<Claims>,
+    memo_id: web::Path<Uuid>,
+    req: web::Json<ShareMemoRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    require_memo_permission(&pool, &memo_id, &user_id, PermissionType::Manage).await?;
+
+    let permission = match req.permission.as_str() {
+        "read" => PermissionType::Read,
+        "write" => PermissionType::Write,
+        "manage" => PermissionType::Manage,
+        _ => {
+            return Err(AppError::BadRequest(
+                "permission must be one of: read, write, manage".to_string(),
+            ));
+        }
+    };
+
+    let granted = db::upsert_memo_permission(&pool, &memo_id, &req.user_id, permission).await?;
+
+    Ok(HttpResponse::Ok().json(MemoPermissionResponse {
+        user_id: granted.user_id,
+        permission: granted.permission,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/memos/{memo_id}/share/{user_id}",
+    params(
+        ("memo_id" = Uuid, Path, description = "Memo ID"),
+        ("user_id" = Uuid, Path, description = "Collaborator's user ID")
+    ),
+    responses(
+        (status = 200, description = "Share revoked"),
+        (status = 403, description = "Manage access required"),
+        (status = 404, description = "Memo not found or collaborator had no share"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    tag = "memos"
+)]
+pub async fn revoke_memo_share(
+    pool: web::Data<Pool<Postgres>>,
+    claims: web::ReqData<Claims>,
+    path: web::Path<(Uuid, Uuid)>,
+) -> Result<HttpResponse, AppError> {
+    let requester_id = Uuid::parse_str(&claims.sub)?;
+
+    let (memo_id, collaborator_id) = path.into_inner();
+
+    require_memo_permission(&pool, &memo_id, &requester_id, PermissionType::Manage).await?;
+
+    let revoked = db::revoke_memo_permission(&pool, &memo_id, &collaborator_id).await?;
+
+    if revoked {
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Share revoked"
+        })))
+    } else {
+        Err(AppError::NotFound(
+            "Collaborator had no share on this memo".to_string(),
+        ))
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/memos/{memo_id}/publish",
+    params(
+        ("memo_id" = Uuid, Path, description = "Memo ID")
+    ),
+    request_body = PublishMemoRequest,
+    responses(
+        (status = 200, description = "Memo published", body = MemoShareResponse),
+        (status = 403, description = "Manage access required"),
+        (status = 404, description = "Memo not found"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    tag = "memos"
+)]
+pub async fn publish_memo(
+    pool: web::Data<Pool<Postgres>>,
+    claims: web::ReqData<Claims>,
+    memo_id: web::Path<Uuid>,
+    req: web::Json<PublishMemoRequest>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    require_memo_permission(&pool, &memo_id, &user_id, PermissionType::Manage).await?;
+
+    let expires_at = req
+        .expires_in_hours
+        .map(|hours| (chrono::Utc::now() + chrono::Duration::hours(hours)).naive_utc());
+
+    let slug = sharing::generate_slug();
+    let share = db::publish_memo(&pool, &memo_id, &slug, expires_at).await?;
+
+    Ok(HttpResponse::Ok().json(MemoShareResponse {
+        slug: share.slug,
+        expires_at: share.expires_at,
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/memos/{memo_id}/publish",
+    params(
+        ("memo_id" = Uuid, Path, description = "Memo ID")
+    ),
+    responses(
+        (status = 200, description = "Share link revoked"),
+        (status = 403, description = "Manage access required"),
+        (status = 404, description = "Memo not found or not published"),
+        (status = 401, description = "Unauthorized")
+    ),
+    security(
+        ("bearer" = [])
+    ),
+    tag = "memos"
+)]
+pub async fn unpublish_memo(
+    pool: web::Data<Pool<Postgres>>,
+    claims: web::ReqData<Claims>,
+    memo_id: web::Path<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    require_memo_permission(&pool, &memo_id, &user_id, PermissionType::Manage).await?;
+
+    let revoked = db::revoke_memo_publish(&pool, &memo_id).await?;
+
+    if revoked {
+        Ok(HttpResponse::Ok().json(serde_json::json!({
+            "message": "Share link revoked"
+        })))
+    } else {
+        Err(AppError::NotFound("Memo is not published".to_string()))
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/shared/{slug}",
+    params(
+        ("slug" = String, Path, description = "Share link slug")
+    ),
+    responses(
+        (status = 200, description = "Shared memo", body = SharedMemoResponse),
+        (status = 404, description = "Share link not found or expired")
+    ),
+    tag = "memos"
+)]
+pub async fn get_shared_memo(
+    pool: web::Data<Pool<Postgres>>,
+    slug: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let memo = db::get_memo_by_slug(&pool, &slug)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Share link not found or expired".to_string()))?;
+
+    let messages = db::get_shared_memo_messages(&pool, &memo.id).await?;
+    let message_count = messages.len() as i64;
+
+    let message_ids: Vec<Uuid> = messages.iter().map(|message| message.id).collect();
+    let mut attachments_by_message = db::get_attachments_for_messages(&pool, &message_ids).await?;
+
+    let messages: Vec<MemoMessageResponse> = messages
+        .into_iter()
+        .map(|message| {
+            let attachments: Vec<MemoAttachmentResponse> = attachments_by_message
+                .remove(&message.id)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|(att, file)| {
+                    let thumbnail_url = thumbnail_url(file.id, &file.previews);
+
+                    MemoAttachmentResponse {
+                        id: att.id,
+                        filename: file.filename,
+                        mime_type: file.mime_type,
+                        file_size: file.file_size,
+                        thumbnail_url,
+                        created_at: att.created_at,
+                    }
+                })
+                .collect();
+
+            MemoMessageResponse {
+                id: message.id,
+                content: message.content,
+                content_html: Some(message.content_html),
+                role: message.role,
+                attachments,
+                created_at: message.created_at,
+            }
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(SharedMemoResponse {
+        memo: MemoResponse {
+            id: memo.id,
+            title: memo.title,
+            description: memo.description,
+            message_count,
+            created_at: memo.created_at,
+            updated_at: memo.updated_at,
+        },
+        messages,
+    }))
+}
+
+/// Picks the smallest generated preview (see `thumbnails::PREVIEW_SIZES`)
+/// for an attached file, so a memo thumbnail is as cheap as possible to
+/// fetch. `None` for non-image attachments or images still awaiting
+/// ingestion.
+///
+/// This reuses `files.previews` (populated once ingestion generates
+/// thumbnails) rather than a dedicated `thumbnail_path` column, so a
+/// just-attached image has no `thumbnail_url` until ingestion finishes.
+fn thumbnail_url(file_id: Uuid, previews: &Option<serde_json::Value>) -> Option<String> {
+    let smallest_label = crate::thumbnails::PREVIEW_SIZES.first()?.0;
+    crate::files::preview_urls(file_id, previews)
+        .remove(smallest_label)
+}
+
+/// Resolves `user_id`'s permission on `memo_id` and, if it's below
+/// `required`, returns the guard rejection as an `AppError`. Always checks
+/// Read first, so a user with no access at all gets the same 404 a
+/// nonexistent memo would, regardless of `required`.
+async fn require_memo_permission(
+    pool: &Pool<Postgres>,
+    memo_id: &Uuid,
+    user_id: &Uuid,
+    required: PermissionType,
+) -> Result<(), AppError> {
+    let permission = db::resolve_memo_permission(pool, memo_id, user_id).await?;
+
+    permissions::require_read(permission)?;
+
+    match required {
+        PermissionType::Write => permissions::require_write(permission),
+        PermissionType::Manage => permissions::require_manage(permission),
+        _ => Ok(()),
+    }
+}
@@ -1,3 +1,4 @@
+use futures_util::StreamExt;
 use s3::bucket::Bucket;
 use s3::creds::Credentials;
 use s3::region::Region;
@@ -45,6 +46,76 @@ impl MinioClient {
         Ok(object_path)
     }
 
+    /// Pumps a byte stream straight into MinIO without buffering the whole
+    /// object in memory first. Returns the object key and total size written.
+    pub async fn upload_stream<S>(
+        &self,
+        user_id: &Uuid,
+        filename: &str,
+        stream: S,
+    ) -> Result<(String, usize), Box<dyn std::error::Error>>
+    where
+        S: futures_util::Stream<Item = Result<Vec<u8>, std::io::Error>> + Unpin + Send,
+    {
+        let object_path = format!("user-{}/{}", user_id, filename);
+        let size = self.upload_stream_raw(&object_path, stream).await?;
+        Ok((object_path, size))
+    }
+
+    /// Like `upload_stream`, but writes to `object_path` verbatim. Used to
+    /// land an upload at a staging key before its content hash (and
+    /// therefore its final, content-addressed key) is known.
+    pub async fn upload_stream_raw<S>(
+        &self,
+        object_path: &str,
+        stream: S,
+    ) -> Result<usize, Box<dyn std::error::Error>>
+    where
+        S: futures_util::Stream<Item = Result<Vec<u8>, std::io::Error>> + Unpin + Send,
+    {
+        let io_stream = stream.map(|chunk| chunk.map(bytes::Bytes::from));
+        let mut reader = tokio_util::io::StreamReader::new(io_stream);
+
+        let size = self
+            .bucket
+            .put_object_stream(&mut reader, object_path)
+            .await
+            .map_err(|e| format!("MinIO streaming upload error: {}", e))?;
+
+        Ok(size)
+    }
+
+    /// Server-side copies `from` to `to` within the bucket, without pulling
+    /// the object through this process.
+    pub async fn copy_object(
+        &self,
+        from: &str,
+        to: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.bucket
+            .copy_object_internal(from, to)
+            .await
+            .map_err(|e| format!("MinIO copy error: {}", e))?;
+
+        Ok(())
+    }
+
+    /// Like `upload_file`, but writes to `object_path` verbatim instead of
+    /// deriving it from a user id + filename. Used for derived objects
+    /// (previews, etc.) that live alongside an already-computed key.
+    pub async fn put_object(
+        &self,
+        object_path: &str,
+        content: &[u8],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.bucket
+            .put_object(object_path, content)
+            .await
+            .map_err(|e| format!("MinIO upload error: {}", e))?;
+
+        Ok(())
+    }
+
     pub async fn download_file(
         &self,
         object_path: &str,
@@ -58,6 +129,51 @@ impl MinioClient {
         Ok(response.bytes().to_vec())
     }
 
+    /// Fetches only `start..=end` of the object via S3's ranged GetObject,
+    /// so scrubbing a large media file doesn't pull the whole thing through.
+    pub async fn download_file_range(
+        &self,
+        object_path: &str,
+        start: u64,
+        end: u64,
+    ) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+        let response = self
+            .bucket
+            .get_object_range(object_path, start, Some(end))
+            .await
+            .map_err(|e| format!("MinIO ranged download error: {}", e))?;
+
+        Ok(response.bytes().to_vec())
+    }
+
+    /// Total size in bytes of the stored object, used to build
+    /// `Content-Range` headers and validate requested ranges.
+    pub async fn object_size(&self, object_path: &str) -> Result<u64, Box<dyn std::error::Error>> {
+        let (head, _) = self
+            .bucket
+            .head_object(object_path)
+            .await
+            .map_err(|e| format!("MinIO head error: {}", e))?;
+
+        Ok(head.content_length.unwrap_or(0) as u64)
+    }
+
+    /// Signs a time-limited GET URL so a client can fetch `object_path`
+    /// directly from MinIO instead of having it proxied through this
+    /// process. Only meaningful for plaintext objects: an encrypted file's
+    /// ciphertext needs this server to unwrap the DEK and decrypt it, so
+    /// callers must not presign those.
+    pub async fn generate_presigned_get(
+        &self,
+        object_path: &str,
+        ttl_secs: u32,
+    ) -> Result<String, Box<dyn std::error::Error>> {
+        self.bucket
+            .presign_get(object_path, ttl_secs, None)
+            .await
+            .map_err(|e| format!("MinIO presign error: {}", e).into())
+    }
+
     pub async fn delete_file(&self, object_path: &str) -> Result<(), Box<dyn std::error::Error>> {
         self.bucket
             .delete_object(object_path)
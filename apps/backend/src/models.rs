@@ -9,6 +9,13 @@ pub struct User {
     pub email: String,
     #[serde(skip_serializing)]
     pub password_hash: String,
+    /// `"user"` or `"admin"`; admin-only endpoints check this via the access
+    /// token's `role` claim rather than re-querying it per request.
+    pub role: String,
+    /// Set by `POST /api/admin/users/{id}/block`. Checked at login/magic-link/
+    /// OAuth time; `auth::validator` checks the Redis `blocked:{id}` marker
+    /// set alongside it on every authenticated request.
+    pub is_blocked: bool,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
@@ -45,11 +52,52 @@ pub struct UserResponse {
     pub email: String,
 }
 
+/// One active refresh-token session family, i.e. one device/browser that
+/// hasn't logged out or been revoked.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SessionResponse {
+    pub family: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListSessionsResponse {
+    pub sessions: Vec<SessionResponse>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct OAuthCallbackQuery {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct MagicLinkVerifyQuery {
+    pub token: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
     pub email: String,
     pub exp: usize,
+    /// Unique id for this token; refresh tokens use it to detect reuse of an
+    /// already-rotated token.
+    pub jti: String,
+    /// Session id shared by every refresh token rotated from the same
+    /// login (one per device/browser). `None` on access tokens, which
+    /// don't participate in rotation.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub family: Option<String>,
+    /// The user's role at the time this access token was issued; admin
+    /// endpoints check this instead of re-querying Postgres per request.
+    /// `None` on refresh tokens, which never authorize an action directly.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub role: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
@@ -61,6 +109,23 @@ pub struct File {
     pub file_size: i64,
     pub mime_type: Option<String>,
     pub status: String,
+    /// Size label (e.g. `"256"`) to the MinIO object key of that downscaled
+    /// preview. Only populated for raster image uploads.
+    #[schema(value_type = Object)]
+    pub previews: Option<serde_json::Value>,
+    /// Master-key-wrapped data encryption key; `None` for plaintext rows
+    /// (uploaded before per-file encryption existed, or while
+    /// `FILE_ENCRYPTION_KEY` is unset), whose `minio_path` holds plaintext.
+    #[serde(skip_serializing)]
+    #[schema(value_type = Object)]
+    pub enc_key: Option<Vec<u8>>,
+    /// Nonce used to encrypt `minio_path`'s object with the (unwrapped) DEK.
+    #[serde(skip_serializing)]
+    #[schema(value_type = Object)]
+    pub enc_nonce: Option<Vec<u8>>,
+    /// Algorithm identifier, e.g. `"aes-256-gcm"`; `None` means plaintext.
+    #[serde(skip_serializing)]
+    pub enc_alg: Option<String>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
 }
@@ -72,13 +137,55 @@ pub struct FileResponse {
     pub file_size: i64,
     pub mime_type: Option<String>,
     pub status: String,
+    /// Size label (e.g. `"256"`, `"1024"`) to a URL the client can fetch the
+    /// preview from; empty for non-image files.
+    pub previews: std::collections::HashMap<String, String>,
+    /// Hex-encoded SHA-256 of the plaintext content, for the caller to verify
+    /// integrity against. `None` for encrypted files, which aren't stored
+    /// under a content-addressed key.
+    pub content_hash: Option<String>,
     pub created_at: chrono::NaiveDateTime,
 }
 
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct ListFilesQuery {
+    /// Max rows to return; capped and defaulted server-side.
+    pub limit: Option<i64>,
+    /// Opaque `next_cursor` from a previous page; omit for the first page.
+    pub cursor: Option<String>,
+    /// Exact MIME type match, e.g. `application/pdf`.
+    pub mime_type: Option<String>,
+    /// Exact status match: `processing`, `ready`, `not_indexed`, or `failed`.
+    pub status: Option<String>,
+    /// Case-insensitive substring match against the filename.
+    pub filename: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct FileListResponse {
+    pub files: Vec<FileResponse>,
+    /// Total rows matching the filters, ignoring pagination.
+    pub total: i64,
+    /// Pass back as `cursor` to fetch the next page; `None` once exhausted.
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DownloadUrlResponse {
+    /// Presigned MinIO URL; valid for `expires_in` seconds from the time of
+    /// this response.
+    pub url: String,
+    pub expires_in: i64,
+}
+
 #[derive(Debug, Deserialize, ToSchema)]
 pub struct SearchRequest {
     pub query: String,
     pub limit: Option<usize>,
+    /// Boolean expression over chunk metadata, e.g.
+    /// `file_id = "<uuid>" AND chunk_index < 10`. Stored chunks carry only
+    /// `file_id` and `chunk_index`; `>`/`<` accept numbers, not strings.
+    pub filter: Option<String>,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -117,6 +224,17 @@ pub struct ErrorResponse {
     pub error: String,
 }
 
+/// Content-addressed storage record: one row per distinct SHA-256 digest
+/// uploaded, shared across however many `File` rows currently reference it.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[allow(dead_code)]
+pub struct Blob {
+    pub hash: String,
+    pub size: i64,
+    pub mime_type: Option<String>,
+    pub ref_count: i64,
+}
+
 // Memos models
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct Memo {
@@ -150,6 +268,9 @@ pub struct MemoMessage {
     pub memo_id: Uuid,
     pub user_id: Uuid,
     pub content: String,
+    /// Sanitized HTML rendering of `content`, computed once at write time by
+    /// `crate::markdown::render` and cached so reads never re-render.
+    pub content_html: String,
     pub role: String, // "user" or "assistant"
     pub created_at: chrono::NaiveDateTime,
 }
@@ -158,6 +279,9 @@ pub struct MemoMessage {
 pub struct MemoMessageResponse {
     pub id: Uuid,
     pub content: String,
+    /// `None` when the request asked for `?render=false`; otherwise the
+    /// cached sanitized-HTML rendering of `content`.
+    pub content_html: Option<String>,
     pub role: String,
     pub attachments: Vec<MemoAttachmentResponse>,
     pub created_at: chrono::NaiveDateTime,
@@ -168,6 +292,13 @@ pub struct CreateMemoMessageRequest {
     pub content: String,
 }
 
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct GetMemoMessagesQuery {
+    /// Set to `false` to get raw `content` only (`content_html` is `null`),
+    /// e.g. when loading a message back into an editor. Defaults to `true`.
+    pub render: Option<bool>,
+}
+
 #[derive(Debug, Serialize, Deserialize, FromRow, ToSchema)]
 pub struct MemoAttachment {
     pub id: Uuid,
@@ -182,5 +313,63 @@ pub struct MemoAttachmentResponse {
     pub filename: String,
     pub mime_type: Option<String>,
     pub file_size: i64,
+    /// URL for the smallest generated preview (see `thumbnails::PREVIEW_SIZES`),
+    /// so clients can render a thumbnail without downloading the original.
+    /// `None` for non-image attachments or images still awaiting ingestion.
+    pub thumbnail_url: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+/// A collaborator's access grant on a memo; the owner needs no row here
+/// since `db::resolve_memo_permission` treats ownership as implicit Manage.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MemoPermission {
+    pub memo_id: Uuid,
+    pub user_id: Uuid,
+    pub permission: String,
     pub created_at: chrono::NaiveDateTime,
 }
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct ShareMemoRequest {
+    pub user_id: Uuid,
+    /// One of `read`, `write`, `manage`.
+    pub permission: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MemoPermissionResponse {
+    pub user_id: Uuid,
+    pub permission: String,
+}
+
+/// A published, unauthenticated read-only link for a memo. Looked up by
+/// `slug` in `GET /api/shared/{slug}`; `expires_at` of `None` never expires.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MemoShare {
+    pub slug: String,
+    pub memo_id: Uuid,
+    pub created_at: chrono::NaiveDateTime,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Debug, Deserialize, ToSchema)]
+pub struct PublishMemoRequest {
+    /// How long the link stays valid; omit for a link that never expires.
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct MemoShareResponse {
+    pub slug: String,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Body of `GET /api/shared/{slug}`: the memo and its messages, with the
+/// same shape `MemoResponse`/`MemoMessageResponse` use elsewhere so clients
+/// can reuse their existing rendering code for a shared view.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SharedMemoResponse {
+    pub memo: MemoResponse,
+    pub messages: Vec<MemoMessageResponse>,
+}
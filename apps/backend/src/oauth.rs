@@ -0,0 +1,348 @@
+//! OAuth2 authorization-code sign-in (Google, GitHub) alongside the
+//! password flow in `auth.rs`. `oauth_start` redirects to the provider with
+//! a PKCE challenge; `oauth_callback` exchanges the code, resolves the
+//! provider's email to a local account, and issues the same access/refresh
+//! JWT pair `auth::login` does — the rest of the session lifecycle doesn't
+//! care how the user signed in.
+
+use crate::auth::{
+    create_access_token, create_refresh_token, store_refresh_token, ACCESS_TOKEN_EXPIRY_HOURS,
+};
+use crate::db;
+use crate::errors::AppError;
+use crate::models::{AuthResponse, OAuthCallbackQuery, UserResponse};
+use actix_web::{web, HttpResponse};
+use base64::Engine;
+use bcrypt::{hash, DEFAULT_COST};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::{Pool, Postgres};
+use std::env;
+use uuid::Uuid;
+
+/// How long a `state`/PKCE verifier survives in Redis before the round-trip
+/// to the provider and back must have completed.
+const OAUTH_STATE_TTL_SECONDS: u64 = 600;
+
+struct ProviderConfig {
+    authorize_url: &'static str,
+    token_url: &'static str,
+    userinfo_url: &'static str,
+    scope: &'static str,
+}
+
+fn provider_config(provider: &str) -> Result<ProviderConfig, AppError> {
+    match provider {
+        "google" => Ok(ProviderConfig {
+            authorize_url: "https://accounts.google.com/o/oauth2/v2/auth",
+            token_url: "https://oauth2.googleapis.com/token",
+            userinfo_url: "https://www.googleapis.com/oauth2/v3/userinfo",
+            scope: "openid email profile",
+        }),
+        "github" => Ok(ProviderConfig {
+            authorize_url: "https://github.com/login/oauth/authorize",
+            token_url: "https://github.com/login/oauth/access_token",
+            userinfo_url: "https://api.github.com/user",
+            scope: "read:user user:email",
+        }),
+        _ => Err(AppError::NotFound(format!(
+            "Unknown OAuth provider: {}",
+            provider
+        ))),
+    }
+}
+
+fn client_id(provider: &str) -> String {
+    env::var(format!("{}_CLIENT_ID", provider.to_uppercase())).unwrap_or_default()
+}
+
+fn client_secret(provider: &str) -> String {
+    env::var(format!("{}_CLIENT_SECRET", provider.to_uppercase())).unwrap_or_default()
+}
+
+fn redirect_uri(provider: &str) -> String {
+    env::var(format!("{}_REDIRECT_URI", provider.to_uppercase())).unwrap_or_else(|_| {
+        format!(
+            "http://localhost:8080/api/auth/oauth/{}/callback",
+            provider
+        )
+    })
+}
+
+fn random_url_safe_token(byte_len: usize) -> String {
+    let mut bytes = vec![0u8; byte_len];
+    OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// RFC 7636 `S256` code challenge derived from `code_verifier`.
+fn pkce_code_challenge(code_verifier: &str) -> String {
+    let digest = Sha256::digest(code_verifier.as_bytes());
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(digest)
+}
+
+fn oauth_state_key(state: &str) -> String {
+    format!("oauth_state:{}", state)
+}
+
+#[derive(Serialize, Deserialize)]
+struct OAuthState {
+    provider: String,
+    code_verifier: String,
+}
+
+async fn store_oauth_state(
+    redis_client: &redis::Client,
+    state: &str,
+    oauth_state: &OAuthState,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut con = redis_client.get_async_connection().await?;
+    let payload = serde_json::to_string(oauth_state)?;
+    con.set_ex::<_, _, ()>(oauth_state_key(state), payload, OAUTH_STATE_TTL_SECONDS)
+        .await?;
+    Ok(())
+}
+
+/// Consumes (gets and deletes) the stored state so a `state` value can only
+/// complete the round-trip once.
+async fn take_oauth_state(
+    redis_client: &redis::Client,
+    state: &str,
+) -> Result<Option<OAuthState>, Box<dyn std::error::Error>> {
+    let mut con = redis_client.get_async_connection().await?;
+    let key = oauth_state_key(state);
+
+    let payload: Option<String> = con.get(&key).await?;
+    con.del::<_, ()>(&key).await?;
+
+    Ok(match payload {
+        Some(payload) => Some(serde_json::from_str(&payload)?),
+        None => None,
+    })
+}
+
+#[derive(serde::Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(serde::Deserialize)]
+struct GoogleUserInfo {
+    email: String,
+    #[serde(default)]
+    email_verified: bool,
+}
+
+/// GitHub's `/user` endpoint only exposes `email` when the user has opted
+/// their primary address into their public profile, which is not the
+/// default; `/user/emails` (also gated behind the `user:email` scope) lists
+/// every address on the account along with its verification state.
+#[derive(serde::Deserialize)]
+struct GithubEmail {
+    email: String,
+    primary: bool,
+    verified: bool,
+}
+
+/// Exchanges `code` for a provider access token, then fetches the user's
+/// email: Google's userinfo endpoint returns it directly along with its
+/// `email_verified` flag, which must be set, while GitHub
+/// needs a second call to `/user/emails` and a `primary && verified` filter
+/// (`/user`'s `email` field is unverified and, by default, absent).
+async fn fetch_verified_email(
+    provider: &str,
+    config: &ProviderConfig,
+    code: &str,
+    code_verifier: &str,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let client = reqwest::Client::new();
+
+    let token_response = client
+        .post(config.token_url)
+        .header("Accept", "application/json")
+        .form(&[
+            ("client_id", client_id(provider)),
+            ("client_secret", client_secret(provider)),
+            ("code", code.to_string()),
+            ("redirect_uri", redirect_uri(provider)),
+            ("grant_type", "authorization_code".to_string()),
+            ("code_verifier", code_verifier.to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?
+        .json::<TokenResponse>()
+        .await?;
+
+    let email = match provider {
+        "google" => {
+            let info = client
+                .get(config.userinfo_url)
+                .bearer_auth(&token_response.access_token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<GoogleUserInfo>()
+                .await?;
+            if !info.email_verified {
+                return Err("Google account email is not verified".into());
+            }
+            info.email
+        }
+        "github" => {
+            // Don't trust `/user`'s `email`: it's only populated when the
+            // user has made their primary address public, which isn't the
+            // default. `/user/emails` lists every address with its
+            // verification state instead, so we can pick the one GitHub
+            // itself has verified rather than trusting an unverified,
+            // attacker-settable address for account linking.
+            let emails = client
+                .get("https://api.github.com/user/emails")
+                .bearer_auth(&token_response.access_token)
+                .header("User-Agent", "k3s-memos-backend")
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<Vec<GithubEmail>>()
+                .await?;
+
+            emails
+                .into_iter()
+                .find(|e| e.primary && e.verified)
+                .map(|e| e.email)
+                .ok_or("GitHub account has no verified primary email")?
+        }
+        _ => return Err("unsupported provider".into()),
+    };
+
+    Ok(email)
+}
+
+/// Finds the account `email` already belongs to, or creates one with an
+/// unusable random password hash — an OAuth-only account can never log in
+/// via `auth::login` unless it later sets a password.
+async fn get_or_create_oauth_user(
+    pool: &Pool<Postgres>,
+    email: &str,
+) -> Result<crate::models::User, AppError> {
+    let unusable_password_hash = hash(Uuid::new_v4().to_string(), DEFAULT_COST)
+        .map_err(|e| AppError::Internal(format!("hash error: {}", e)))?;
+
+    Ok(db::get_or_create_user_by_email(pool, email, &unusable_password_hash).await?)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oauth/{provider}/start",
+    params(
+        ("provider" = String, Path, description = "OAuth provider: `google` or `github`")
+    ),
+    responses(
+        (status = 302, description = "Redirect to the provider's authorize URL"),
+        (status = 404, description = "Unknown provider")
+    ),
+    tag = "auth"
+)]
+pub async fn oauth_start(
+    redis_client: web::Data<redis::Client>,
+    provider: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let provider = provider.into_inner();
+    let config = provider_config(&provider)?;
+
+    let state = random_url_safe_token(32);
+    let code_verifier = random_url_safe_token(32);
+    let code_challenge = pkce_code_challenge(&code_verifier);
+
+    store_oauth_state(
+        &redis_client,
+        &state,
+        &OAuthState {
+            provider: provider.clone(),
+            code_verifier,
+        },
+    )
+    .await?;
+
+    let mut authorize_url = reqwest::Url::parse(config.authorize_url)
+        .map_err(|e| AppError::Internal(format!("invalid authorize URL: {}", e)))?;
+    authorize_url
+        .query_pairs_mut()
+        .append_pair("client_id", &client_id(&provider))
+        .append_pair("redirect_uri", &redirect_uri(&provider))
+        .append_pair("response_type", "code")
+        .append_pair("scope", config.scope)
+        .append_pair("state", &state)
+        .append_pair("code_challenge", &code_challenge)
+        .append_pair("code_challenge_method", "S256");
+    let authorize_url = authorize_url.to_string();
+
+    Ok(HttpResponse::Found()
+        .append_header(("Location", authorize_url))
+        .finish())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oauth/{provider}/callback",
+    params(
+        ("provider" = String, Path, description = "OAuth provider: `google` or `github`"),
+        OAuthCallbackQuery
+    ),
+    responses(
+        (status = 200, description = "Signed in successfully", body = AuthResponse),
+        (status = 401, description = "Invalid or expired state"),
+        (status = 403, description = "Account is blocked"),
+        (status = 404, description = "Unknown provider")
+    ),
+    tag = "auth"
+)]
+pub async fn oauth_callback(
+    pool: web::Data<Pool<Postgres>>,
+    redis_client: web::Data<redis::Client>,
+    provider: web::Path<String>,
+    query: web::Query<OAuthCallbackQuery>,
+) -> Result<HttpResponse, AppError> {
+    let provider = provider.into_inner();
+    let config = provider_config(&provider)?;
+
+    let oauth_state = take_oauth_state(&redis_client, &query.state)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired state".to_string()))?;
+
+    if oauth_state.provider != provider {
+        return Err(AppError::Unauthorized("Provider mismatch".to_string()));
+    }
+
+    let email = fetch_verified_email(&provider, &config, &query.code, &oauth_state.code_verifier)
+        .await
+        .map_err(|e| AppError::Internal(format!("oauth provider error: {}", e)))?;
+
+    let user = get_or_create_oauth_user(&pool, &email).await?;
+
+    if user.is_blocked {
+        return Err(AppError::Forbidden("Account is blocked".to_string()));
+    }
+
+    let access_token = create_access_token(&user.id, &user.email, &user.role)?;
+
+    let family = Uuid::new_v4().to_string();
+    let (refresh_token, refresh_jti) = create_refresh_token(&user.id, &user.email, &family)?;
+
+    store_refresh_token(&redis_client, &user.id, &family, &refresh_jti)
+        .await?;
+
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        access_token,
+        refresh_token,
+        token_type: "Bearer".to_string(),
+        expires_in: ACCESS_TOKEN_EXPIRY_HOURS * 3600,
+        user: UserResponse {
+            id: user.id,
+            email: user.email,
+        },
+    }))
+}
@@ -0,0 +1,80 @@
+//! Role-based access control for shared memos. A memo's owner implicitly
+//! holds `Manage`; everyone else's access is whatever row (if any) exists in
+//! `memo_permissions`, resolved by `db::resolve_memo_permission`.
+
+use crate::errors::AppError;
+
+/// Access level a user has on a memo, ordered so `Manage > Write > Read >
+/// NoPermission` — derived `Ord` compares variants in declaration order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionType {
+    NoPermission,
+    Read,
+    Write,
+    Manage,
+}
+
+impl PermissionType {
+    pub fn can_read(self) -> bool {
+        self >= PermissionType::Read
+    }
+
+    pub fn can_write(self) -> bool {
+        self >= PermissionType::Write
+    }
+
+    pub fn can_manage(self) -> bool {
+        self >= PermissionType::Manage
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PermissionType::NoPermission => "none",
+            PermissionType::Read => "read",
+            PermissionType::Write => "write",
+            PermissionType::Manage => "manage",
+        }
+    }
+
+    /// Parses a `memo_permissions.permission` value; unrecognized strings
+    /// (including `"none"`, which is never stored) resolve to `NoPermission`
+    /// rather than erroring, since that's the safe default anyway.
+    pub fn parse(value: &str) -> PermissionType {
+        match value {
+            "read" => PermissionType::Read,
+            "write" => PermissionType::Write,
+            "manage" => PermissionType::Manage,
+            _ => PermissionType::NoPermission,
+        }
+    }
+}
+
+/// Collaborators below `Read` shouldn't learn the memo exists at all, so the
+/// guard for read access looks just like a missing-memo 404.
+pub fn require_read(permission: PermissionType) -> Result<(), AppError> {
+    if permission.can_read() {
+        Ok(())
+    } else {
+        Err(AppError::NotFound("Memo not found".to_string()))
+    }
+}
+
+pub fn require_write(permission: PermissionType) -> Result<(), AppError> {
+    if permission.can_write() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(
+            "Write access to this memo is required".to_string(),
+        ))
+    }
+}
+
+pub fn require_manage(permission: PermissionType) -> Result<(), AppError> {
+    if permission.can_manage() {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(
+            "Manage access to this memo is required".to_string(),
+        ))
+    }
+}
@@ -1,17 +1,188 @@
+use base64::Engine;
 use qdrant_client::prelude::*;
 use qdrant_client::qdrant::vectors_config::Config;
 use qdrant_client::qdrant::{
-    CreateCollection, Distance, SearchPoints, VectorParams, VectorsConfig,
+    CreateCollection, Distance, Filter, NamedVectors, SearchPoints, SparseIndexConfig,
+    SparseVector, SparseVectorParams, SparseVectorsConfig, VectorParams, Vectors, VectorsConfig,
 };
+use std::collections::HashMap;
 use std::env;
 use std::sync::Arc;
 use uuid::Uuid;
 
+const DENSE_VECTOR_NAME: &str = "dense";
+const SPARSE_VECTOR_NAME: &str = "sparse";
+/// RRF smoothing constant; higher values flatten the influence of rank position.
+const RRF_K: f32 = 60.0;
+/// Format marker stored alongside a gzip-compressed `text` payload so
+/// uncompressed points written before this was enabled keep decoding fine.
+const TEXT_ENCODING_GZIP: &str = "gzip";
+
+fn payload_compression_enabled() -> bool {
+    env::var("COMPRESS_CHUNK_PAYLOADS")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+/// Gzips `text` and base64-encodes the result so it fits Qdrant's JSON payload.
+fn compress_chunk_text(text: &str) -> Result<String, std::io::Error> {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    let compressed = encoder.finish()?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(compressed))
+}
+
+fn decompress_chunk_text(encoded: &str) -> Result<String, Box<dyn std::error::Error>> {
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    let compressed = base64::engine::general_purpose::STANDARD.decode(encoded)?;
+    let mut decoder = GzDecoder::new(&compressed[..]);
+    let mut text = String::new();
+    decoder.read_to_string(&mut text)?;
+    Ok(text)
+}
+
 #[derive(Clone)]
 pub struct QdrantService {
     client: Arc<QdrantClient>,
 }
 
+/// Term frequencies for a single chunk, keyed by a stable token hash.
+fn term_counts(text: &str) -> HashMap<u32, u32> {
+    let mut counts = HashMap::new();
+    for token in text.split_whitespace() {
+        let normalized = token.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase();
+        if normalized.is_empty() {
+            continue;
+        }
+        let id = crc32fast::hash(normalized.as_bytes());
+        *counts.entry(id).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// BM25-style idf: ln(1 + (N - n + 0.5) / (n + 0.5)).
+fn idf(corpus_size: usize, docs_with_term: usize) -> f32 {
+    let n = corpus_size as f32;
+    let df = docs_with_term as f32;
+    ((n - df + 0.5) / (df + 0.5) + 1.0).ln()
+}
+
+/// Builds a sparse BM25-weighted vector for each chunk using corpus-wide document
+/// frequencies computed across the batch being upserted.
+fn build_sparse_vectors(chunks: &[(usize, String, Vec<f32>)]) -> Vec<SparseVector> {
+    let per_chunk_counts: Vec<HashMap<u32, u32>> = chunks
+        .iter()
+        .map(|(_, text, _)| term_counts(text))
+        .collect();
+
+    let corpus_size = per_chunk_counts.len().max(1);
+    let mut doc_frequency: HashMap<u32, usize> = HashMap::new();
+    for counts in &per_chunk_counts {
+        for term in counts.keys() {
+            *doc_frequency.entry(*term).or_insert(0) += 1;
+        }
+    }
+
+    per_chunk_counts
+        .into_iter()
+        .map(|counts| {
+            let mut indices = Vec::with_capacity(counts.len());
+            let mut values = Vec::with_capacity(counts.len());
+            for (term, tf) in counts {
+                let df = *doc_frequency.get(&term).unwrap_or(&1);
+                let weight = tf as f32 * idf(corpus_size, df);
+                indices.push(term);
+                values.push(weight);
+            }
+            SparseVector { indices, values }
+        })
+        .collect()
+}
+
+/// Builds a sparse vector for a query string using raw term frequencies; the
+/// per-document idf weighting used at ingest time isn't available at query time.
+fn build_sparse_query(text: &str) -> Option<SparseVector> {
+    let counts = term_counts(text);
+    if counts.is_empty() {
+        return None;
+    }
+
+    let mut indices = Vec::with_capacity(counts.len());
+    let mut values = Vec::with_capacity(counts.len());
+    for (term, tf) in counts {
+        indices.push(term);
+        values.push(tf as f32);
+    }
+
+    Some(SparseVector { indices, values })
+}
+
+fn reciprocal_rank_fusion(
+    dense: Vec<(Uuid, String, f32)>,
+    sparse: Vec<(Uuid, String, f32)>,
+    limit: usize,
+) -> Vec<(Uuid, String, f32)> {
+    let mut fused: HashMap<Uuid, (String, f32)> = HashMap::new();
+
+    for (rank, (id, text, _)) in dense.into_iter().enumerate() {
+        let entry = fused.entry(id).or_insert((text, 0.0));
+        entry.1 += 1.0 / (RRF_K + rank as f32);
+    }
+    for (rank, (id, text, _)) in sparse.into_iter().enumerate() {
+        let entry = fused.entry(id).or_insert((text, 0.0));
+        entry.1 += 1.0 / (RRF_K + rank as f32);
+    }
+
+    let mut results: Vec<(Uuid, String, f32)> = fused
+        .into_iter()
+        .map(|(id, (text, score))| (id, text, score))
+        .collect();
+    results.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(limit);
+    results
+}
+
+/// Pulls `(file_id, chunk text, score)` out of a scored point, transparently
+/// gunzipping `text` when the point carries the `text_enc = "gzip"` marker.
+fn extract_scored_chunk(
+    point: qdrant_client::qdrant::ScoredPoint,
+) -> Option<(Uuid, String, f32)> {
+    let file_id = point
+        .payload
+        .get("file_id")
+        .and_then(|v| v.as_str())
+        .and_then(|s| Uuid::parse_str(s).ok())?;
+
+    let raw_text = point.payload.get("text").and_then(|v| v.as_str())?;
+
+    let is_gzip = point
+        .payload
+        .get("text_enc")
+        .and_then(|v| v.as_str())
+        .map(|enc| enc == TEXT_ENCODING_GZIP)
+        .unwrap_or(false);
+
+    let text = if is_gzip {
+        match decompress_chunk_text(raw_text) {
+            Ok(text) => text,
+            Err(e) => {
+                log::warn!("Failed to decompress chunk payload, skipping: {}", e);
+                return None;
+            }
+        }
+    } else {
+        raw_text.to_string()
+    };
+
+    Some((file_id, text, point.score))
+}
+
 impl QdrantService {
     pub async fn new() -> Result<Self, Box<dyn std::error::Error>> {
         let url = env::var("QDRANT_URL").unwrap_or_else(|_| "http://localhost:6333".to_string());
@@ -24,21 +195,44 @@ impl QdrantService {
     pub async fn ensure_collection_exists(
         &self,
         user_id: &Uuid,
+        vector_size: u64,
     ) -> Result<(), Box<dyn std::error::Error>> {
         let collection_name = format!("user_{}", user_id.to_string().replace("-", "_"));
 
         match self.client.collection_info(&collection_name).await {
             Ok(_) => Ok(()),
             Err(_) => {
+                let mut sparse_config = HashMap::new();
+                sparse_config.insert(
+                    SPARSE_VECTOR_NAME.to_string(),
+                    SparseVectorParams {
+                        index: Some(SparseIndexConfig::default()),
+                        ..Default::default()
+                    },
+                );
+
+                let mut dense_config = HashMap::new();
+                dense_config.insert(
+                    DENSE_VECTOR_NAME.to_string(),
+                    VectorParams {
+                        size: vector_size,
+                        distance: Distance::Cosine.into(),
+                        ..Default::default()
+                    },
+                );
+
                 self.client
                     .create_collection(&CreateCollection {
                         collection_name: collection_name.clone(),
                         vectors_config: Some(VectorsConfig {
-                            config: Some(Config::Params(VectorParams {
-                                size: 1536,
-                                distance: Distance::Cosine.into(),
-                                ..Default::default()
-                            })),
+                            config: Some(Config::ParamsMap(
+                                qdrant_client::qdrant::VectorParamsMap {
+                                    map: dense_config,
+                                },
+                            )),
+                        }),
+                        sparse_vectors_config: Some(SparseVectorsConfig {
+                            map: sparse_config,
                         }),
                         ..Default::default()
                     })
@@ -57,21 +251,41 @@ impl QdrantService {
         let collection_name = format!("user_{}", user_id.to_string().replace("-", "_"));
 
         use qdrant_client::qdrant::Value;
-        use std::collections::HashMap;
+
+        let sparse_vectors = build_sparse_vectors(&chunks);
+        let compress = payload_compression_enabled();
 
         let points: Vec<PointStruct> = chunks
             .into_iter()
-            .map(|(idx, text, embedding)| {
+            .zip(sparse_vectors)
+            .map(|((idx, text, embedding), sparse)| {
                 let mut payload: HashMap<String, Value> = HashMap::new();
                 payload.insert("file_id".to_string(), Value::from(file_id.to_string()));
                 payload.insert("chunk_index".to_string(), Value::from(idx as i64));
-                payload.insert("text".to_string(), Value::from(text));
 
-                PointStruct::new(
-                    Uuid::new_v4().to_string(),
-                    embedding,
-                    Payload::from(payload),
-                )
+                match compress.then(|| compress_chunk_text(&text)) {
+                    Some(Ok(compressed)) => {
+                        payload.insert("text".to_string(), Value::from(compressed));
+                        payload.insert(
+                            "text_enc".to_string(),
+                            Value::from(TEXT_ENCODING_GZIP.to_string()),
+                        );
+                    }
+                    Some(Err(e)) => {
+                        log::warn!("Payload compression failed, storing plain text: {}", e);
+                        payload.insert("text".to_string(), Value::from(text));
+                    }
+                    None => {
+                        payload.insert("text".to_string(), Value::from(text));
+                    }
+                }
+
+                let vectors: Vectors = NamedVectors::default()
+                    .add_vector(DENSE_VECTOR_NAME, embedding)
+                    .add_vector_sparse(SPARSE_VECTOR_NAME, sparse)
+                    .into();
+
+                PointStruct::new(Uuid::new_v4().to_string(), vectors, Payload::from(payload))
             })
             .collect();
 
@@ -82,21 +296,94 @@ impl QdrantService {
         Ok(())
     }
 
+    async fn search_named(
+        &self,
+        collection_name: &str,
+        vector_name: &str,
+        vector: Vec<f32>,
+        limit: usize,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(Uuid, String, f32)>, Box<dyn std::error::Error>> {
+        let search_result = self
+            .client
+            .search_points(&SearchPoints {
+                collection_name: collection_name.to_string(),
+                vector,
+                vector_name: Some(vector_name.to_string()),
+                limit: limit as u64,
+                with_payload: Some(true.into()),
+                filter,
+                ..Default::default()
+            })
+            .await?;
+
+        let results = search_result
+            .result
+            .into_iter()
+            .filter_map(extract_scored_chunk)
+            .collect();
+
+        Ok(results)
+    }
+
+    /// Hybrid dense+sparse search fused with Reciprocal Rank Fusion. Falls back to
+    /// dense-only scoring when the sparse search returns nothing or fails (e.g. the
+    /// query has no overlapping terms). The dense search uses the named `dense`
+    /// vector, so a collection without it returns an error.
     pub async fn search(
         &self,
         user_id: &Uuid,
+        query_text: &str,
         query_vector: Vec<f32>,
         limit: usize,
+        filter: Option<Filter>,
     ) -> Result<Vec<(Uuid, String, f32)>, Box<dyn std::error::Error>> {
         let collection_name = format!("user_{}", user_id.to_string().replace("-", "_"));
 
+        let dense_results = self
+            .search_named(
+                &collection_name,
+                DENSE_VECTOR_NAME,
+                query_vector,
+                limit,
+                filter.clone(),
+            )
+            .await?;
+
+        let sparse_results = match build_sparse_query(query_text) {
+            Some(sparse) => self
+                .search_sparse(&collection_name, sparse, limit, filter)
+                .await
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        if sparse_results.is_empty() {
+            return Ok(dense_results);
+        }
+
+        Ok(reciprocal_rank_fusion(dense_results, sparse_results, limit))
+    }
+
+    async fn search_sparse(
+        &self,
+        collection_name: &str,
+        sparse: SparseVector,
+        limit: usize,
+        filter: Option<Filter>,
+    ) -> Result<Vec<(Uuid, String, f32)>, Box<dyn std::error::Error>> {
         let search_result = self
             .client
             .search_points(&SearchPoints {
-                collection_name,
-                vector: query_vector,
+                collection_name: collection_name.to_string(),
+                sparse_indices: Some(qdrant_client::qdrant::SparseIndices {
+                    data: sparse.indices,
+                }),
+                vector: sparse.values,
+                vector_name: Some(SPARSE_VECTOR_NAME.to_string()),
                 limit: limit as u64,
                 with_payload: Some(true.into()),
+                filter,
                 ..Default::default()
             })
             .await?;
@@ -104,21 +391,7 @@ impl QdrantService {
         let results = search_result
             .result
             .into_iter()
-            .filter_map(|point| {
-                let file_id = point
-                    .payload
-                    .get("file_id")
-                    .and_then(|v| v.as_str())
-                    .and_then(|s| Uuid::parse_str(s).ok())?;
-
-                let text = point
-                    .payload
-                    .get("text")
-                    .and_then(|v| v.as_str())
-                    .map(|s| s.to_string())?;
-
-                Some((file_id, text, point.score))
-            })
+            .filter_map(extract_scored_chunk)
             .collect();
 
         Ok(results)
@@ -132,7 +405,7 @@ impl QdrantService {
         let collection_name = format!("user_{}", user_id.to_string().replace("-", "_"));
 
         // Delete all points with this file_id using a filter
-        use qdrant_client::qdrant::{Condition, Filter};
+        use qdrant_client::qdrant::Condition;
 
         let filter = Filter::must([Condition::matches(
             "file_id",
@@ -146,7 +419,3 @@ impl QdrantService {
         Ok(())
     }
 }
-
-pub async fn create_mock_embedding(_text: &str) -> Vec<f32> {
-    vec![0.1; 1536]
-}
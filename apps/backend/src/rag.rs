@@ -1,19 +1,33 @@
 use crate::db;
+use crate::embedding::Embedder;
+use crate::errors::AppError;
+use crate::filter::parse_and_compile;
+use crate::llm::LlmClient;
 use crate::models::Claims;
 use crate::models::{
-    RagQueryRequest, RagQueryResponse, SearchRequest, SearchResponse, SearchResult,
+    ErrorResponse, RagQueryRequest, RagQueryResponse, SearchRequest, SearchResponse, SearchResult,
 };
-use crate::qdrant_service::{create_mock_embedding, QdrantService};
-use actix_web::{web, Error, HttpResponse};
+use crate::qdrant_service::QdrantService;
+use actix_web::{web, HttpResponse};
 use sqlx::{Pool, Postgres};
+use std::sync::Arc;
 use uuid::Uuid;
 
+async fn embed_one(
+    embedder: &dyn Embedder,
+    text: &str,
+) -> Result<Vec<f32>, Box<dyn std::error::Error>> {
+    let mut vectors = embedder.embed_batch(&[text.to_string()]).await?;
+    Ok(vectors.pop().unwrap_or_default())
+}
+
 #[utoipa::path(
     post,
     path = "/api/search",
     request_body = SearchRequest,
     responses(
         (status = 200, description = "Search results", body = SearchResponse),
+        (status = 400, description = "Malformed filter expression", body = ErrorResponse),
         (status = 401, description = "Unauthorized")
     ),
     security(
@@ -23,24 +37,31 @@ use uuid::Uuid;
 pub async fn search(
     pool: web::Data<Pool<Postgres>>,
     qdrant: web::Data<QdrantService>,
+    embedder: web::Data<Arc<dyn Embedder>>,
     claims: web::ReqData<Claims>,
     req: web::Json<SearchRequest>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
+
+    let filter = match req.filter.as_deref().map(parse_and_compile) {
+        Some(Ok(filter)) => Some(filter),
+        Some(Err(e)) => {
+            return Ok(HttpResponse::BadRequest().json(ErrorResponse {
+                error: format!("invalid filter: {}", e),
+            }));
+        }
+        None => None,
+    };
 
-    let query_embedding = create_mock_embedding(&req.query).await;
+    let query_embedding = embed_one(embedder.as_ref().as_ref(), &req.query)
+        .await
+        .map_err(|e| AppError::Internal(format!("embedding error: {}", e)))?;
     let limit = req.limit.unwrap_or(10);
 
     let search_results = qdrant
-        .search(&user_id, query_embedding, limit)
+        .search(&user_id, &req.query, query_embedding, limit, filter)
         .await
-        .map_err(|e| {
-            log::error!("Qdrant search error: {}", e);
-            actix_web::error::ErrorInternalServerError("Search error")
-        })?;
+        .map_err(|e| AppError::Internal(format!("qdrant search error: {}", e)))?;
 
     let mut results = Vec::new();
     for (file_id, chunk_text, score) in search_results {
@@ -76,24 +97,22 @@ pub async fn search(
 pub async fn rag_query(
     pool: web::Data<Pool<Postgres>>,
     qdrant: web::Data<QdrantService>,
+    embedder: web::Data<Arc<dyn Embedder>>,
+    llm: web::Data<Arc<dyn LlmClient>>,
     claims: web::ReqData<Claims>,
     req: web::Json<RagQueryRequest>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
-    let query_embedding = create_mock_embedding(&req.query).await;
+    let query_embedding = embed_one(embedder.as_ref().as_ref(), &req.query)
+        .await
+        .map_err(|e| AppError::Internal(format!("embedding error: {}", e)))?;
     let context_limit = req.context_limit.unwrap_or(5);
 
     let search_results = qdrant
-        .search(&user_id, query_embedding, context_limit)
+        .search(&user_id, &req.query, query_embedding, context_limit, None)
         .await
-        .map_err(|e| {
-            log::error!("Qdrant search error: {}", e);
-            actix_web::error::ErrorInternalServerError("Search error")
-        })?;
+        .map_err(|e| AppError::Internal(format!("qdrant search error: {}", e)))?;
 
     let mut context_parts = Vec::new();
     let mut sources = Vec::new();
@@ -111,8 +130,10 @@ pub async fn rag_query(
         }
     }
 
-    let context = context_parts.join("\n\n");
-    let answer = generate_answer(&req.query, &context);
+    let answer = llm
+        .complete(&req.query, &context_parts)
+        .await
+        .map_err(|e| AppError::Internal(format!("llm error: {}", e)))?;
 
     db::save_chat_message(&pool, &user_id, "user", &req.query)
         .await
@@ -138,18 +159,10 @@ pub async fn rag_query(
 pub async fn get_history(
     pool: web::Data<Pool<Postgres>>,
     claims: web::ReqData<Claims>,
-) -> Result<HttpResponse, Error> {
-    let user_id = Uuid::parse_str(&claims.sub).map_err(|e| {
-        log::error!("UUID parse error: {}", e);
-        actix_web::error::ErrorBadRequest("Invalid user ID")
-    })?;
+) -> Result<HttpResponse, AppError> {
+    let user_id = Uuid::parse_str(&claims.sub)?;
 
-    let messages = db::get_chat_history(&pool, &user_id, 50)
-        .await
-        .map_err(|e| {
-            log::error!("Database error: {}", e);
-            actix_web::error::ErrorInternalServerError("Database error")
-        })?;
+    let messages = db::get_chat_history(&pool, &user_id, 50).await?;
 
     let chat_messages: Vec<serde_json::Value> = messages
         .into_iter()
@@ -164,21 +177,3 @@ pub async fn get_history(
 
     Ok(HttpResponse::Ok().json(chat_messages))
 }
-
-fn generate_answer(query: &str, context: &str) -> String {
-    if context.is_empty() {
-        return format!(
-            "I don't have enough information in your uploaded documents to answer: \"{}\".\n\n\
-             Please upload relevant documents first.",
-            query
-        );
-    }
-
-    format!(
-        "Based on your documents, here's what I found regarding \"{}\":\n\n\
-         {}\n\n\
-         Note: This is a simple RAG implementation. For production, integrate with OpenAI or another LLM API.",
-        query,
-        &context[..context.len().min(500)]
-    )
-}
@@ -1,4 +1,4 @@
-use redis::Client;
+use redis::{AsyncCommands, Client};
 use std::env;
 
 pub fn create_redis_client() -> Result<Client, redis::RedisError> {
@@ -7,3 +7,66 @@ pub fn create_redis_client() -> Result<Client, redis::RedisError> {
     log::info!("Connecting to Redis at {}", redis_url);
     Client::open(redis_url)
 }
+
+/// Redis list used as a work queue for the file ingestion pipeline.
+pub const INGESTION_QUEUE_KEY: &str = "ingest:queue";
+
+pub async fn enqueue_job(
+    redis_client: &Client,
+    queue_key: &str,
+    payload: &str,
+) -> Result<(), redis::RedisError> {
+    let mut con = redis_client.get_async_connection().await?;
+    con.lpush::<_, _, ()>(queue_key, payload).await
+}
+
+fn processing_queue_key(queue_key: &str) -> String {
+    format!("{}:processing", queue_key)
+}
+
+/// Reliable-queue pop: atomically moves a job from `queue_key` onto its
+/// processing list instead of removing it outright, so a worker that
+/// crashes mid-job doesn't lose it. Call `ack_job` once the job is done
+/// (success or not) to remove it from the processing list.
+pub async fn dequeue_job_reliable(
+    redis_client: &Client,
+    queue_key: &str,
+    timeout_secs: f64,
+) -> Result<Option<String>, redis::RedisError> {
+    let mut con = redis_client.get_async_connection().await?;
+    con.brpoplpush(queue_key, &processing_queue_key(queue_key), timeout_secs)
+        .await
+}
+
+/// Removes a completed job's payload from the processing list.
+pub async fn ack_job(
+    redis_client: &Client,
+    queue_key: &str,
+    payload: &str,
+) -> Result<(), redis::RedisError> {
+    let mut con = redis_client.get_async_connection().await?;
+    con.lrem::<_, _, ()>(&processing_queue_key(queue_key), 1, payload)
+        .await
+}
+
+/// Moves anything still sitting in the processing list back onto the main
+/// queue. Call once at worker startup to recover jobs that were mid-flight
+/// when the process was last killed. Returns the number of jobs recovered.
+pub async fn recover_processing_queue(
+    redis_client: &Client,
+    queue_key: &str,
+) -> Result<usize, redis::RedisError> {
+    let mut con = redis_client.get_async_connection().await?;
+    let processing_key = processing_queue_key(queue_key);
+
+    let mut recovered = 0;
+    while con
+        .rpoplpush::<_, _, Option<String>>(&processing_key, queue_key)
+        .await?
+        .is_some()
+    {
+        recovered += 1;
+    }
+
+    Ok(recovered)
+}
@@ -0,0 +1,18 @@
+//! Short, unguessable slugs for public read-only memo share links. Encodes
+//! a random 64-bit id with `sqids` rather than incrementing a counter, so a
+//! slug never leaks how many memos have been published or in what order.
+
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sqids::Sqids;
+
+/// Generates a new slug: the sqids encoding of a random 64-bit id, usually
+/// around a dozen alphanumeric characters. Collisions are astronomically
+/// unlikely but not impossible; callers should retry on a unique-constraint
+/// violation rather than assume this is infallible.
+pub fn generate_slug() -> String {
+    let id = OsRng.next_u64();
+
+    let sqids = Sqids::default();
+    sqids.encode(&[id]).unwrap_or_else(|_| id.to_string())
+}
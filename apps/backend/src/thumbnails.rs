@@ -0,0 +1,61 @@
+//! Downscaled preview generation for image uploads. Runs as part of ingestion
+//! right alongside RAG indexing, so previews are ready by the time a client
+//! polls `FileResponse.status` back to `ready`.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::GenericImageView;
+use std::io::Cursor;
+
+/// Bounding-box sizes (in pixels, long edge) generated for every previewable
+/// image. Kept small and fixed for now; exposed as the `previews` map keys.
+pub const PREVIEW_SIZES: &[(&str, u32)] = &[("256", 256), ("1024", 1024)];
+
+/// Re-encode quality for the JPEG previews.
+const PREVIEW_JPEG_QUALITY: u8 = 80;
+
+/// Source images wider or taller than this are rejected rather than decoded,
+/// to avoid decompression-bomb inputs chewing through memory/CPU.
+const MAX_SOURCE_DIMENSION: u32 = 12_000;
+
+pub fn is_previewable(mime_type: &str) -> bool {
+    mime_type.starts_with("image/")
+}
+
+/// Decodes `file_data` and renders a JPEG preview for each entry in
+/// `PREVIEW_SIZES`, each no larger than its bounding box (aspect preserved).
+/// Returns `(size_label, jpeg_bytes)` pairs; skips generation entirely (with
+/// an `Ok(vec![])`) for inputs over `MAX_SOURCE_DIMENSION`.
+pub fn generate_previews(file_data: &[u8]) -> Result<Vec<(String, Vec<u8>)>, Box<dyn std::error::Error>> {
+    // Read only the header first, so an oversized source is never decoded.
+    let (width, height) = image::io::Reader::new(Cursor::new(file_data))
+        .with_guessed_format()?
+        .into_dimensions()?;
+    if width > MAX_SOURCE_DIMENSION || height > MAX_SOURCE_DIMENSION {
+        log::warn!(
+            "Skipping preview generation for oversized image ({}x{})",
+            width,
+            height
+        );
+        return Ok(Vec::new());
+    }
+
+    let img = image::load_from_memory(file_data)?;
+
+    let mut previews = Vec::with_capacity(PREVIEW_SIZES.len());
+    for (label, max_dimension) in PREVIEW_SIZES {
+        // Never upscale: a source already inside the box is re-encoded as is.
+        let thumbnail = if width <= *max_dimension && height <= *max_dimension {
+            img.clone()
+        } else {
+            img.resize(*max_dimension, *max_dimension, FilterType::Lanczos3)
+        };
+
+        let mut buf = Vec::new();
+        JpegEncoder::new_with_quality(Cursor::new(&mut buf), PREVIEW_JPEG_QUALITY)
+            .encode_image(&thumbnail)?;
+        previews.push((label.to_string(), buf));
+    }
+
+    Ok(previews)
+}
@@ -0,0 +1,112 @@
+//! Magic-byte content sniffing so an upload can't lie about its type via a
+//! spoofed filename extension alone.
+
+use std::env;
+
+/// Sniffs the true MIME type from an object's leading bytes. Returns `None`
+/// when nothing recognizable matches (e.g. plain text, which has no
+/// reliable magic number and is left to the extension guess).
+pub fn sniff_mime_type(head: &[u8]) -> Option<&'static str> {
+    if head.starts_with(b"%PDF-") {
+        return Some("application/pdf");
+    }
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        return Some("image/png");
+    }
+    if head.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some("image/jpeg");
+    }
+    if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        return Some("image/gif");
+    }
+    if head.len() >= 12 && &head[0..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        return Some("image/webp");
+    }
+    if head.starts_with(b"PK\x03\x04") {
+        // docx/xlsx/pptx/zip all share this magic; distinguishing between
+        // them would require inspecting the archive's internal manifest.
+        return Some("application/zip");
+    }
+    if head.starts_with(&[0x1F, 0x8B]) {
+        return Some("application/gzip");
+    }
+
+    None
+}
+
+#[derive(Debug)]
+pub enum ValidationError {
+    /// The sniffed type doesn't match what the filename extension implied.
+    MimeMismatch { claimed: String, sniffed: String },
+    /// The effective type isn't on the configured allow-list.
+    Disallowed(String),
+}
+
+impl std::fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationError::MimeMismatch { claimed, sniffed } => write!(
+                f,
+                "file content ({}) does not match its extension ({})",
+                sniffed, claimed
+            ),
+            ValidationError::Disallowed(mime) => {
+                write!(f, "uploads of type {} are not allowed", mime)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// OOXML formats are zip containers under a different extension, so they
+/// sniff as `application/zip` even though their claimed MIME type (from the
+/// extension) is the Office-specific one. Both are treated as a match
+/// rather than a spoofed upload.
+const OOXML_MIME_TYPES: &[&str] = &[
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+];
+
+fn allowed_mime_types() -> Option<Vec<String>> {
+    env::var("UPLOAD_ALLOWED_MIME_TYPES").ok().map(|v| {
+        v.split(',')
+            .map(|s| s.trim().to_lowercase())
+            .filter(|s| !s.is_empty())
+            .collect()
+    })
+}
+
+/// Cross-checks `claimed_mime_type` (derived from the filename extension)
+/// against the sniffed magic bytes and the operator's allow-list, returning
+/// the type that should actually be stored for this upload.
+pub fn validate_upload(
+    header: &[u8],
+    claimed_mime_type: &str,
+) -> Result<String, ValidationError> {
+    let effective = match sniff_mime_type(header) {
+        Some(sniffed) if sniffed == claimed_mime_type => sniffed.to_string(),
+        Some("application/zip") if OOXML_MIME_TYPES.contains(&claimed_mime_type) => {
+            // A genuine .docx/.xlsx/.pptx is a zip container, so it sniffs
+            // as "application/zip"; keep the more specific Office type the
+            // extension claimed instead of downgrading it to plain zip.
+            claimed_mime_type.to_string()
+        }
+        Some(sniffed) => {
+            return Err(ValidationError::MimeMismatch {
+                claimed: claimed_mime_type.to_string(),
+                sniffed: sniffed.to_string(),
+            });
+        }
+        None => claimed_mime_type.to_string(),
+    };
+
+    if let Some(allowed) = allowed_mime_types() {
+        if !allowed.contains(&effective.to_lowercase()) {
+            return Err(ValidationError::Disallowed(effective));
+        }
+    }
+
+    Ok(effective)
+}